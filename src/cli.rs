@@ -0,0 +1,368 @@
+//! Headless commands, run instead of the overlay when arguments are given.
+
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, RgbaImage};
+use serde::{
+    Deserialize,
+    de::value::{Error as ValueError, StrDeserializer},
+};
+
+use crate::color;
+use crate::config::{self, FilterMode, Transform};
+
+const USAGE: &str = "\
+Usage:
+    screen_filter apply --mode <MODE> [--transform <TRANSFORM>] <INPUT> <OUTPUT>
+    screen_filter apply --mode <MODE> [--transform <TRANSFORM>] <INPUT>... <OUTPUT_DIR>
+    screen_filter check-config <PATH>
+
+Filters PNG, JPEG or TIFF images the same way the overlay filters the screen.
+<MODE> is written like `mode` in the config, e.g. `LabGrayscale`,
+`{ Protanopia = { severity = 0.5 } }` or `{ Lut = 'print.cube' }`. Repeat
+--mode to chain filters like `pipeline` in the config. <TRANSFORM> is one of
+FlipHorizontal, FlipVertical or Rotate180.
+
+check-config validates a config file and loads the files it refers to,
+printing every problem found. Exits with 1 if there are any.";
+
+#[derive(Debug)]
+pub enum Command {
+    Apply {
+        passes: Vec<FilterMode>,
+        transform: Transform,
+        inputs: Vec<PathBuf>,
+        output: PathBuf,
+    },
+    CheckConfig {
+        path: PathBuf,
+    },
+}
+
+impl Command {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        match args.next().as_deref() {
+            Some("apply") => {
+                let mut passes = Vec::new();
+                let mut transform = Transform::None;
+                let mut paths = Vec::new();
+
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--mode" => {
+                            let value = args.next().ok_or("Missing value for --mode")?;
+                            let mut mode = parse_mode(&value)?;
+                            // Files are relative to the working directory.
+                            mode.resolve(Path::new(""))?;
+                            passes.push(mode);
+                        }
+                        "--transform" => {
+                            let value = args.next().ok_or("Missing value for --transform")?;
+                            transform =
+                                Transform::deserialize(StrDeserializer::<ValueError>::new(&value))
+                                    .map_err(|err| err.to_string())?;
+                        }
+                        _ => paths.push(PathBuf::from(arg)),
+                    }
+                }
+
+                if passes.is_empty() {
+                    return Err("Missing --mode".into());
+                }
+                if !passes.iter().all(FilterMode::supports_cpu) {
+                    return Err("Custom shaders can't be applied to image files".into());
+                }
+                let output = paths.pop().ok_or("Missing input and output paths")?;
+                if paths.is_empty() {
+                    return Err("Missing output path".into());
+                }
+
+                Ok(Command::Apply {
+                    passes,
+                    transform,
+                    inputs: paths,
+                    output,
+                })
+            }
+            Some("check-config") => {
+                let path = args.next().ok_or("Missing config path")?;
+                if let Some(arg) = args.next() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                Ok(Command::CheckConfig {
+                    path: PathBuf::from(path),
+                })
+            }
+            Some(command) => Err(format!("Unknown command: {}", command)),
+            None => Err("No command given".into()),
+        }
+    }
+
+    pub fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Command::Apply {
+                passes,
+                transform,
+                inputs,
+                output,
+            } => {
+                if inputs.len() == 1 && !output.is_dir() {
+                    return apply(&passes, transform, &inputs[0], &output);
+                }
+
+                std::fs::create_dir_all(&output)?;
+                for input in &inputs {
+                    let file_name = input
+                        .file_name()
+                        .ok_or_else(|| format!("Not a file: {}", input.display()))?;
+                    apply(&passes, transform, input, &output.join(file_name))?;
+                }
+                Ok(())
+            }
+            Command::CheckConfig { path } => match config::check_config(&path) {
+                Ok(_) => {
+                    println!("{} is valid", path.display());
+                    Ok(())
+                }
+                Err(errors) => Err(errors
+                    .iter()
+                    .map(|err| format!("{}: {}", path.display(), err))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .into()),
+            },
+        }
+    }
+}
+
+/// Parses a mode name, or an inline table like in the config for modes with
+/// options, e.g. `{ Protanopia = { severity = 0.5 } }`.
+fn parse_mode(value: &str) -> Result<FilterMode, String> {
+    if value.trim_start().starts_with('{') {
+        #[derive(Deserialize)]
+        struct Mode {
+            mode: FilterMode,
+        }
+        return toml::from_str::<Mode>(&format!("mode = {}", value))
+            .map(|m| m.mode)
+            .map_err(|err| err.to_string());
+    }
+
+    FilterMode::deserialize(StrDeserializer::<ValueError>::new(value))
+        .map_err(|err| err.to_string())
+}
+
+fn apply(
+    passes: &[FilterMode],
+    transform: Transform,
+    input: &Path,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let image =
+        image::open(input).map_err(|err| format!("Unable to open {}: {}", input.display(), err))?;
+    let mut image: RgbaImage = image.into_rgba8();
+    let (width, height) = image.dimensions();
+    // Mirrored first like on screen, so the heatmap legend stays upright.
+    transform.apply_cpu(&mut image, width, height);
+    color::apply_pipeline(passes, &mut image, width, height);
+
+    // The filtered image is always opaque, and JPEG can't store alpha anyway.
+    DynamicImage::ImageRgba8(image)
+        .into_rgb8()
+        .save(output)
+        .map_err(|err| format!("Unable to save {}: {}", output.display(), err))?;
+    println!("{} -> {}", input.display(), output.display());
+
+    Ok(())
+}
+
+/// Runs the command given on the command line, returns the exit code.
+pub fn run(args: impl Iterator<Item = String>) -> i32 {
+    let command = match Command::parse(args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return 2;
+        }
+    };
+
+    match command.run() {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parse_apply() {
+        let Ok(Command::Apply {
+            passes,
+            transform,
+            inputs,
+            output,
+        }) = parse(&["apply", "a.png", "--mode", "LabGrayscale", "b.jpg", "out"])
+        else {
+            panic!("apply not parsed");
+        };
+        assert!(matches!(passes.as_slice(), [FilterMode::LabGrayscale]));
+        assert_eq!(transform, Transform::None);
+        assert_eq!(inputs, [PathBuf::from("a.png"), PathBuf::from("b.jpg")]);
+        assert_eq!(output, PathBuf::from("out"));
+
+        let Ok(Command::Apply {
+            passes, transform, ..
+        }) = parse(&[
+            "apply",
+            "--mode",
+            "{ Tritanopia = { severity = 0.25 } }",
+            "--mode",
+            "OklabGrayscale",
+            "--transform",
+            "FlipHorizontal",
+            "a.png",
+            "b.png",
+        ])
+        else {
+            panic!("apply not parsed");
+        };
+        assert!(matches!(
+            passes.as_slice(),
+            [FilterMode::Tritanopia { severity }, FilterMode::OklabGrayscale] if *severity == 0.25
+        ));
+        assert_eq!(transform, Transform::FlipHorizontal);
+    }
+
+    #[test]
+    fn check_config() {
+        let Ok(Command::CheckConfig { path }) = parse(&["check-config", "config.toml"]) else {
+            panic!("check-config not parsed");
+        };
+        assert_eq!(path, PathBuf::from("config.toml"));
+        assert!(parse(&["check-config"]).is_err());
+        assert!(parse(&["check-config", "a.toml", "b.toml"]).is_err());
+
+        let dir = std::env::temp_dir().join(format!("screen_filter_check_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "toggle = \"ctrl+alt+c\"\nfreeze = \"ctrl+alt+f\"\nmode = { Lut = \"missing.cube\" }\nlaunch_on_startup = false\nrefresh_rate = 0\n",
+        )
+        .unwrap();
+        let err = Command::CheckConfig { path: path.clone() }
+            .run()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(&format!(
+                "{}: line 3, column 1: mode: Unable to read",
+                path.display()
+            )),
+            "{}",
+            err
+        );
+
+        std::fs::write(
+            dir.join("missing.cube"),
+            "LUT_3D_SIZE 2\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n",
+        )
+        .unwrap();
+        assert!(Command::CheckConfig { path: path.clone() }.run().is_ok());
+
+        std::fs::write(
+            &path,
+            "toggle = \"ctrl+alt+c\"\nfreeze = \"ctrl+alt+f\"\npipeline = [\"LabGrayscale\", { Custom = \"missing.hlsl\" }]\nlaunch_on_startup = false\nrefresh_rate = 0\n",
+        )
+        .unwrap();
+        let err = Command::CheckConfig { path: path.clone() }
+            .run()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.starts_with(&format!(
+                "{}: line 3, column 1: pipeline: Unable to read",
+                path.display()
+            )),
+            "{}",
+            err
+        );
+        std::fs::write(dir.join("missing.hlsl"), "").unwrap();
+        assert!(Command::CheckConfig { path }.run().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["filter"]).is_err());
+        assert!(parse(&["apply", "a.png", "b.png"]).is_err());
+        assert!(parse(&["apply", "--mode", "Sepia", "a.png", "b.png"]).is_err());
+        assert!(parse(&["apply", "--mode", "LabGrayscale", "a.png"]).is_err());
+        assert!(
+            parse(&[
+                "apply",
+                "--mode",
+                "LabGrayscale",
+                "--transform",
+                "Rotate90",
+                "a.png",
+                "b.png"
+            ])
+            .is_err()
+        );
+        assert!(
+            parse(&[
+                "apply",
+                "--mode",
+                "{ Custom = \"check.hlsl\" }",
+                "a.png",
+                "b.png"
+            ])
+            .is_err()
+        );
+        assert!(
+            parse(&[
+                "apply",
+                "--mode",
+                "{ Protanopia = { severity = 2.0 } }",
+                "a.png",
+                "b.png"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn apply_file() {
+        let dir = std::env::temp_dir().join(format!("screen_filter_cli_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("red.png");
+        let output = dir.join("red_gray.png");
+        RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]))
+            .save(&input)
+            .unwrap();
+
+        apply(
+            &[FilterMode::LabGrayscale],
+            Transform::None,
+            &input,
+            &output,
+        )
+        .unwrap();
+        let filtered = image::open(&output).unwrap().into_rgb8();
+        assert!(filtered.pixels().all(|px| px.0 == [136, 136, 136]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,923 @@
+//! CPU implementation of the per-pixel transforms in `shaders/`.
+//!
+//! Every function here mirrors its HLSL counterpart line by line, so the color
+//! math can be checked without a GPU.
+
+use crate::config::{
+    Blur, Chroma, Clipping, ClippingStyle, Contrast, ContrastMetric, Edges, FilterMode, Heatmap,
+    Hue, Lightness, Posterize,
+};
+
+pub fn gamma_to_linear(x: f32) -> f32 {
+    if x <= 0.0 {
+        return x;
+    }
+    if x <= 0.04045 {
+        return x / 12.92;
+    }
+    ((x + 0.055) / 1.055).powf(2.4)
+}
+
+pub fn linear_to_gamma(x: f32) -> f32 {
+    if x <= 0.0 {
+        return x;
+    }
+    if x <= 0.0031308 {
+        return x * 12.92;
+    }
+    1.055 * x.powf(1.0 / 2.4) - 0.055
+}
+
+/// CIE Lab companding of a tristimulus value relative to the white point.
+fn lab_f(t: f32) -> f32 {
+    const CIE_EPSILON: f32 = 216.0 / 24389.0;
+    const CIE_KAPPA: f32 = 24389.0 / 27.0;
+    if t > CIE_EPSILON {
+        t.powf(1.0 / 3.0)
+    } else {
+        (CIE_KAPPA * t + 16.0) / 116.0
+    }
+}
+
+/// CIE Lab `L`, scaled to `0..=1`, of a linear sRGB color.
+pub fn lab_lightness(r: f32, g: f32, b: f32) -> f32 {
+    let y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    1.16 * lab_f(y) - 0.16
+}
+
+/// CIE LCh `C*` of a linear sRGB color, relative to D65.
+pub fn lab_chroma(r: f32, g: f32, b: f32) -> f32 {
+    let x = r * 0.4124564 + g * 0.3575761 + b * 0.1804375;
+    let y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    let z = r * 0.0193339 + g * 0.119192 + b * 0.9503041;
+    let fx = lab_f(x / 0.95047);
+    let fy = lab_f(y);
+    let fz = lab_f(z / 1.08883);
+    let a = 500.0 * (fx - fy);
+    let b = 200.0 * (fy - fz);
+    (a * a + b * b).sqrt()
+}
+
+/// OkLab of a linear sRGB color, `L` before [`toe`] is applied.
+pub fn oklab(red: f32, green: f32, blue: f32) -> [f32; 3] {
+    let l = 0.41222146 * red + 0.53633255 * green + 0.051445995 * blue;
+    let m = 0.2119035 * red + 0.6806995 * green + 0.10739696 * blue;
+    let s = 0.08830246 * red + 0.28171885 * green + 0.6299787 * blue;
+    let l_ = l.powf(1.0 / 3.0);
+    let m_ = m.powf(1.0 / 3.0);
+    let s_ = s.powf(1.0 / 3.0);
+    [
+        0.21045426 * l_ + 0.7936178 * m_ - 0.004072047 * s_,
+        1.9779985 * l_ - 2.4285922 * m_ + 0.4505937 * s_,
+        0.025904037 * l_ + 0.78277177 * m_ - 0.80867577 * s_,
+    ]
+}
+
+/// OkLab `L` of a linear sRGB color, before [`toe`] is applied.
+pub fn oklab_lightness(red: f32, green: f32, blue: f32) -> f32 {
+    oklab(red, green, blue)[0]
+}
+
+/// OkLCh `C` of a linear sRGB color.
+pub fn oklch_chroma(red: f32, green: f32, blue: f32) -> f32 {
+    let [_, a, b] = oklab(red, green, blue);
+    (a * a + b * b).sqrt()
+}
+
+/// WCAG 2.x relative luminance of an sRGB color, the same `Y` as in
+/// [`lab_lightness`].
+pub fn relative_luminance(color: [f32; 3]) -> f32 {
+    let [r, g, b] = color.map(gamma_to_linear);
+    r * 0.2126729 + g * 0.7151522 + b * 0.072175
+}
+
+/// WCAG 2.x contrast ratio of two relative luminances, from 1 to 21.
+pub fn contrast_ratio(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Largest APCA `Lc` between two sRGB colors, white text on black.
+pub const APCA_MAX: f32 = 108.0;
+
+/// APCA 0.0.98G screen luminance of an sRGB color, near blacks softly clamped.
+fn apca_luminance(color: [f32; 3]) -> f32 {
+    let [r, g, b] = color.map(|c| c.max(0.0).powf(2.4));
+    let y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    if y > 0.022 {
+        y
+    } else {
+        y + (0.022 - y).powf(1.414)
+    }
+}
+
+/// APCA 0.0.98G lightness contrast `Lc` of sRGB `text` on `background`,
+/// positive for dark text on a light background.
+pub fn apca_contrast(text: [f32; 3], background: [f32; 3]) -> f32 {
+    let text = apca_luminance(text);
+    let background = apca_luminance(background);
+    if (background - text).abs() < 0.0005 {
+        return 0.0;
+    }
+    let lc = if background > text {
+        let s = (background.powf(0.56) - text.powf(0.57)) * 1.14;
+        if s < 0.1 { 0.0 } else { s - 0.027 }
+    } else {
+        let s = (background.powf(0.65) - text.powf(0.62)) * 1.14;
+        if s > -0.1 { 0.0 } else { s + 0.027 }
+    };
+    lc * 100.0
+}
+
+/// Okhsl toe function, remaps OkLab `L` to be closer to CIE Lab `L`.
+pub fn toe(x: f32) -> f32 {
+    const K1: f32 = 0.206;
+    const K2: f32 = 0.03;
+    const K3: f32 = (1.0 + K1) / (1.0 + K2);
+    0.5 * (K3 * x - K1 + ((K3 * x - K1) * (K3 * x - K1) + 4.0 * K2 * K3 * x).sqrt())
+}
+
+/// Color vision deficiency simulation in linear sRGB at full severity, from
+/// Machado, Oliveira and Fernandes, "A Physiologically-based Model for
+/// Simulation of Color Vision Deficiency", 2009.
+pub const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+pub const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+pub const TRITANOPIA: [[f32; 3]; 3] = [
+    [1.255528, -0.076749, -0.178779],
+    [-0.078411, 0.930809, 0.147602],
+    [0.004733, 0.691367, 0.303900],
+];
+/// Only the luminance is left.
+pub const ACHROMATOPSIA: [[f32; 3]; 3] = [[0.2126729, 0.7151522, 0.072175]; 3];
+
+/// Blends a linear sRGB color towards how it looks through `matrix`, with
+/// `severity` in `0..=1`.
+pub fn simulate_cvd(matrix: &[[f32; 3]; 3], severity: f32, rgb: [f32; 3]) -> [f32; 3] {
+    let simulated =
+        matrix.map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).clamp(0.0, 1.0));
+    std::array::from_fn(|i| rgb[i] + (simulated[i] - rgb[i]) * severity)
+}
+
+impl Contrast {
+    /// Gray level of an sRGB color, the contrast ratio on a log scale for WCAG
+    /// so equal steps are equal ratios, `|Lc|` for APCA.
+    pub fn level(&self, color: [f32; 3]) -> f32 {
+        match self.metric {
+            ContrastMetric::Wcag => {
+                let ratio = contrast_ratio(
+                    relative_luminance(color),
+                    relative_luminance(self.background),
+                );
+                ratio.ln() / 21.0f32.ln()
+            }
+            ContrastMetric::Apca => apca_contrast(color, self.background).abs() / APCA_MAX,
+        }
+    }
+}
+
+impl Posterize {
+    /// Index of the band lightness `l` falls into.
+    pub fn band(&self, l: f32) -> usize {
+        let mut band = 0;
+        for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+            if l >= *breakpoint {
+                band = i + 1;
+            }
+        }
+        band
+    }
+}
+
+/// Size of the heatmap legend and its distance to the bottom right corner, in
+/// pixels.
+pub const LEGEND_WIDTH: f32 = 16.0;
+pub const LEGEND_HEIGHT: f32 = 256.0;
+pub const LEGEND_MARGIN: f32 = 24.0;
+
+/// Color of the evenly spaced gradient `stops` at `t` in `0..=1`, blended in
+/// sRGB.
+pub fn gradient(stops: &[[f32; 3]], t: f32) -> [f32; 3] {
+    let x = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let i = (x.floor() as usize).min(stops.len() - 2);
+    let f = x - i as f32;
+    let [a, b] = [stops[i], stops[i + 1]];
+    std::array::from_fn(|c| a[c] + (b[c] - a[c]) * f)
+}
+
+impl Heatmap {
+    /// Lightness the legend shows at pixel center `(x, y)` of a `width` by
+    /// `height` output, `None` outside of it.
+    pub fn legend_value(x: f32, y: f32, width: f32, height: f32) -> Option<f32> {
+        let right = width - LEGEND_MARGIN;
+        let bottom = height - LEGEND_MARGIN;
+        if x < right - LEGEND_WIDTH || x >= right || y < bottom - LEGEND_HEIGHT || y >= bottom {
+            return None;
+        }
+        Some((bottom - y) / LEGEND_HEIGHT)
+    }
+
+    fn draw_legend(&self, pixels: &mut [u8], width: u32, height: u32) {
+        for (i, px) in pixels.chunks_exact_mut(4).enumerate() {
+            let x = (i % width as usize) as f32 + 0.5;
+            let y = (i / width as usize) as f32 + 0.5;
+            if let Some(l) = Self::legend_value(x, y, width as f32, height as f32) {
+                let [r, g, b] = gradient(&self.colors, l);
+                px[0] = unorm(r);
+                px[1] = unorm(g);
+                px[2] = unorm(b);
+            }
+        }
+    }
+}
+
+/// Chroma at the end of the gradient, a bit above the most saturated sRGB
+/// color.
+pub const OKLCH_MAX_CHROMA: f32 = 0.33;
+pub const LAB_MAX_CHROMA: f32 = 134.0;
+
+impl Chroma {
+    /// Chroma of a linear sRGB color, in the units of `clip`.
+    pub fn chroma(&self, r: f32, g: f32, b: f32) -> f32 {
+        match self.model {
+            Lightness::Oklab => oklch_chroma(r, g, b),
+            Lightness::Lab => lab_chroma(r, g, b),
+        }
+    }
+
+    pub fn max_chroma(&self) -> f32 {
+        match self.model {
+            Lightness::Oklab => OKLCH_MAX_CHROMA,
+            Lightness::Lab => LAB_MAX_CHROMA,
+        }
+    }
+}
+
+impl Hue {
+    /// How much of the color is kept at OkLCh hue `hue` in degrees, 1 within
+    /// the range, fading to 0 over the falloff.
+    pub fn weight(&self, hue: f32) -> f32 {
+        let distance = ((hue - self.center + 540.0) % 360.0 - 180.0).abs();
+        let outside = distance - self.width * 0.5;
+        if self.falloff > 0.0 {
+            (1.0 - outside / self.falloff).clamp(0.0, 1.0)
+        } else if outside <= 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Width of the clipping hatch stripes, in pixels.
+pub const HATCH_WIDTH: u32 = 4;
+
+impl Clipping {
+    /// Warning color of an sRGB color, `None` if nothing is clipped.
+    pub fn warning(&self, color: [f32; 3]) -> Option<[f32; 3]> {
+        let values = color.map(|c| (c * 255.0).round());
+        let low = values.map(|v| v <= self.shadows as f32);
+        let high = values.map(|v| v >= self.highlights as f32);
+        if low.iter().all(|&l| l) {
+            Some(self.shadow_color)
+        } else if high.iter().all(|&h| h) {
+            Some(self.highlight_color)
+        } else if self.channels && (low.contains(&true) || high.contains(&true)) {
+            Some(self.channel_color)
+        } else {
+            None
+        }
+    }
+
+    /// Marks the sRGB color of pixel `(x, y)` if it's clipped.
+    pub fn shade(&self, color: [f32; 3], x: u32, y: u32) -> [f32; 3] {
+        let Some(warning) = self.warning(color) else {
+            return color;
+        };
+        match self.style {
+            ClippingStyle::Hatch if ((x + y) / HATCH_WIDTH).is_multiple_of(2) => warning,
+            ClippingStyle::Hatch => color,
+            ClippingStyle::Tint => {
+                std::array::from_fn(|c| color[c] + (warning[c] - color[c]) * 0.5)
+            }
+        }
+    }
+}
+
+impl Blur {
+    /// Standard deviation of the kernel, the radius covers three of them.
+    pub fn sigma(&self) -> f32 {
+        self.radius / 3.0
+    }
+
+    /// Samples taken on each side of a pixel.
+    pub fn taps(&self) -> i32 {
+        self.radius.ceil() as i32
+    }
+
+    /// One pass of the separable kernel along `(dx, dy)` in linear light, edges
+    /// are clamped like the sampler does.
+    fn blur_pass(&self, pixels: &mut [u8], width: u32, height: u32, (dx, dy): (i32, i32)) {
+        let linear: Vec<[f32; 3]> = pixels
+            .chunks_exact(4)
+            .map(|px| [px[0], px[1], px[2]].map(|c| gamma_to_linear(c as f32 / 255.0)))
+            .collect();
+        let sigma = self.sigma();
+        let radius = self.taps();
+
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let mut sum = [0.0; 3];
+                let mut total = 0.0;
+                for i in -radius..=radius {
+                    let w = (-0.5 * (i * i) as f32 / (sigma * sigma)).exp();
+                    let sx = (x + dx * i).clamp(0, width as i32 - 1);
+                    let sy = (y + dy * i).clamp(0, height as i32 - 1);
+                    let c = linear[(sy * width as i32 + sx) as usize];
+                    for ch in 0..3 {
+                        sum[ch] += w * c[ch];
+                    }
+                    total += w;
+                }
+
+                let px = &mut pixels[(y * width as i32 + x) as usize * 4..][..4];
+                for ch in 0..3 {
+                    px[ch] = unorm(linear_to_gamma(sum[ch] / total));
+                }
+                px[3] = 255;
+            }
+        }
+    }
+}
+
+impl Edges {
+    /// Sobel gradient strength at `(x, y)` of a `width` wide lightness image,
+    /// 1 for a black to white step. Samples are `thickness` pixels apart and
+    /// clamped to the edges.
+    pub fn strength(&self, lightness: &[f32], width: u32, x: i32, y: i32) -> f32 {
+        let height = (lightness.len() / width as usize) as i32;
+        let t = self.thickness as i32;
+        let at = |dx: i32, dy: i32| {
+            let sx = (x + dx * t).clamp(0, width as i32 - 1);
+            let sy = (y + dy * t).clamp(0, height - 1);
+            lightness[(sy * width as i32 + sx) as usize]
+        };
+        let gx =
+            (at(1, -1) + 2.0 * at(1, 0) + at(1, 1)) - (at(-1, -1) + 2.0 * at(-1, 0) + at(-1, 1));
+        let gy =
+            (at(-1, 1) + 2.0 * at(0, 1) + at(1, 1)) - (at(-1, -1) + 2.0 * at(0, -1) + at(1, -1));
+        (gx * gx + gy * gy).sqrt() / 4.0
+    }
+
+    fn apply(&self, pixels: &mut [u8], width: u32, height: u32) {
+        let lightness: Vec<f32> = pixels
+            .chunks_exact(4)
+            .map(|px| {
+                let [r, g, b] = [px[0], px[1], px[2]].map(|c| gamma_to_linear(c as f32 / 255.0));
+                match self.lightness {
+                    Lightness::Oklab => toe(oklab_lightness(r, g, b)),
+                    Lightness::Lab => lab_lightness(r, g, b),
+                }
+            })
+            .collect();
+        let [line, blank] = if self.invert { [255, 0] } else { [0, 255] };
+
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let px = &mut pixels[(y * width as i32 + x) as usize * 4..][..4];
+                if self.strength(&lightness, width, x, y) >= self.threshold {
+                    px[..3].fill(line);
+                } else if !self.overlay {
+                    px[..3].fill(blank);
+                }
+                px[3] = 255;
+            }
+        }
+    }
+}
+
+/// Converts a shader output channel to what the `UNORM` render target stores.
+fn unorm(x: f32) -> u8 {
+    (x.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+impl FilterMode {
+    /// Filters a single sRGB color, each channel in `0..=1`.
+    pub fn apply_color(&self, color: [f32; 3]) -> [f32; 3] {
+        let [r, g, b] = color.map(gamma_to_linear);
+        match self {
+            FilterMode::OklabGrayscale => {
+                let l = toe(oklab_lightness(r, g, b));
+                [l; 3]
+            }
+            FilterMode::LabGrayscale => {
+                let l = lab_lightness(r, g, b);
+                [l; 3]
+            }
+            FilterMode::Contrast(contrast) => [contrast.level(color); 3],
+            FilterMode::Protanopia { severity } => {
+                simulate_cvd(&PROTANOPIA, *severity, [r, g, b]).map(linear_to_gamma)
+            }
+            FilterMode::Deuteranopia { severity } => {
+                simulate_cvd(&DEUTERANOPIA, *severity, [r, g, b]).map(linear_to_gamma)
+            }
+            FilterMode::Tritanopia { severity } => {
+                simulate_cvd(&TRITANOPIA, *severity, [r, g, b]).map(linear_to_gamma)
+            }
+            FilterMode::Achromatopsia { severity } => {
+                simulate_cvd(&ACHROMATOPSIA, *severity, [r, g, b]).map(linear_to_gamma)
+            }
+            FilterMode::Posterize(posterize) => {
+                let l = match posterize.lightness {
+                    Lightness::Oklab => toe(oklab_lightness(r, g, b)),
+                    Lightness::Lab => lab_lightness(r, g, b),
+                };
+                posterize.colors[posterize.band(l)]
+            }
+            FilterMode::Lut(lut) => match &lut.cube {
+                Some(cube) => cube.sample(color),
+                // Not loaded, see `FilterMode::resolve`.
+                None => color,
+            },
+            FilterMode::Heatmap(heatmap) => {
+                let l = match heatmap.lightness {
+                    Lightness::Oklab => toe(oklab_lightness(r, g, b)),
+                    Lightness::Lab => lab_lightness(r, g, b),
+                };
+                gradient(&heatmap.colors, l)
+            }
+            FilterMode::Chroma(chroma) => {
+                let c = chroma.chroma(r, g, b);
+                if chroma.clip.is_some_and(|clip| c > clip) {
+                    return chroma.clip_color;
+                }
+                gradient(&chroma.colors, c / chroma.max_chroma())
+            }
+            FilterMode::Hue(hue) => {
+                let [l, a, b] = oklab(r, g, b);
+                let l = toe(l);
+                let weight = hue.weight(b.atan2(a).to_degrees());
+                color.map(|c| l + (c - l) * weight)
+            }
+            // Needs the neighboring pixels, see `FilterMode::apply_cpu`.
+            FilterMode::Blur(_) | FilterMode::Edges(_) => color,
+            // Depends on the position, see `FilterMode::apply_cpu`.
+            FilterMode::Clipping(_) => color,
+            // See `FilterMode::supports_cpu`.
+            FilterMode::Custom(_) => color,
+        }
+    }
+
+    /// Filters a tightly packed RGBA8 image in place, alpha is set to opaque
+    /// just like the overlay.
+    pub fn apply_cpu(&self, pixels: &mut [u8], width: u32, height: u32) {
+        assert_eq!(
+            pixels.len(),
+            width as usize * height as usize * 4,
+            "pixel buffer doesn't match {}x{} RGBA8",
+            width,
+            height
+        );
+
+        // Horizontal, then vertical through an intermediate target.
+        if let FilterMode::Blur(blur) = self {
+            blur.blur_pass(pixels, width, height, (1, 0));
+            blur.blur_pass(pixels, width, height, (0, 1));
+            return;
+        }
+        if let FilterMode::Edges(edges) = self {
+            edges.apply(pixels, width, height);
+            return;
+        }
+
+        for (i, px) in pixels.chunks_exact_mut(4).enumerate() {
+            let color = [px[0], px[1], px[2]].map(|c| c as f32 / 255.0);
+            let [r, g, b] = match self {
+                FilterMode::Clipping(clipping) => {
+                    let (x, y) = (i as u32 % width, i as u32 / width);
+                    clipping.shade(color, x, y)
+                }
+                _ => self.apply_color(color),
+            };
+            px[0] = unorm(r);
+            px[1] = unorm(g);
+            px[2] = unorm(b);
+            px[3] = 255;
+        }
+
+        if let FilterMode::Heatmap(heatmap) = self
+            && heatmap.legend
+        {
+            heatmap.draw_legend(pixels, width, height);
+        }
+    }
+}
+
+/// Applies each pass to the output of the previous one. Passes are rounded to
+/// 8 bits in between, just like the overlay's intermediate render targets.
+pub fn apply_pipeline(passes: &[FilterMode], pixels: &mut [u8], width: u32, height: u32) {
+    for pass in passes {
+        pass.apply_cpu(pixels, width, height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn srgb_decode() {
+        assert_eq!(gamma_to_linear(0.0), 0.0);
+        assert_eq!(gamma_to_linear(1.0), 1.0);
+        assert_close(gamma_to_linear(0.04045), 0.0031308, 1e-6);
+        assert_close(gamma_to_linear(0.5), 0.21404114, 1e-6);
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        for x in [0.0, 0.002, 0.04045, 0.5, 1.0] {
+            assert_close(linear_to_gamma(gamma_to_linear(x)), x, 1e-6);
+        }
+    }
+
+    #[test]
+    fn lab_reference() {
+        // CIE L* of the sRGB primaries and #777777.
+        for (srgb, expected) in [
+            ([1.0, 0.0, 0.0], 53.2408),
+            ([0.0, 1.0, 0.0], 87.7347),
+            ([0.0, 0.0, 1.0], 32.2970),
+            ([1.0, 1.0, 1.0], 100.0),
+            ([119.0 / 255.0; 3], 50.0325),
+        ] {
+            let [r, g, b] = srgb.map(gamma_to_linear);
+            assert_close(lab_lightness(r, g, b) * 100.0, expected, 1e-2);
+        }
+    }
+
+    #[test]
+    fn oklab_reference() {
+        // OkLab L of the sRGB primaries, from Björn Ottosson's reference.
+        for (srgb, expected) in [
+            ([1.0, 0.0, 0.0], 0.627955),
+            ([0.0, 1.0, 0.0], 0.866440),
+            ([0.0, 0.0, 1.0], 0.452014),
+            ([1.0, 1.0, 1.0], 1.0),
+        ] {
+            let [r, g, b] = srgb.map(gamma_to_linear);
+            assert_close(oklab_lightness(r, g, b), expected, 1e-4);
+        }
+    }
+
+    #[test]
+    fn toe_reference() {
+        assert_close(toe(0.0), 0.0, 1e-6);
+        assert_close(toe(1.0), 1.0, 1e-6);
+        // Okhsl's toe maps the OkLab L of #777777 close to its CIE L*.
+        let gray = gamma_to_linear(119.0 / 255.0);
+        assert_close(toe(oklab_lightness(gray, gray, gray)), 0.5, 1e-2);
+    }
+
+    #[test]
+    fn cvd_simulation() {
+        // Rows sum to one, so grays are left alone.
+        for matrix in [&PROTANOPIA, &DEUTERANOPIA, &TRITANOPIA, &ACHROMATOPSIA] {
+            for (actual, expected) in simulate_cvd(matrix, 1.0, [0.5; 3]).iter().zip([0.5; 3]) {
+                assert_close(*actual, expected, 1e-5);
+            }
+        }
+
+        assert_eq!(
+            simulate_cvd(&PROTANOPIA, 0.0, [1.0, 0.0, 0.0]),
+            [1.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            simulate_cvd(&PROTANOPIA, 1.0, [1.0, 0.0, 0.0]),
+            [0.152286, 0.114503, 0.0]
+        );
+        let half = simulate_cvd(&DEUTERANOPIA, 0.5, [0.0, 1.0, 0.0]);
+        assert_close(half[0], 0.430323, 1e-6);
+    }
+
+    #[test]
+    fn posterize_bands() {
+        let posterize = Posterize {
+            lightness: Lightness::Lab,
+            breakpoints: vec![0.25, 0.5, 0.75],
+            colors: vec![[0.0; 3], [0.25; 3], [0.5; 3], [1.0; 3]],
+        };
+        assert_eq!(posterize.band(0.0), 0);
+        assert_eq!(posterize.band(0.25), 1);
+        assert_eq!(posterize.band(0.6), 2);
+        assert_eq!(posterize.band(1.0), 3);
+
+        // Red's L* is 53.2.
+        let mut pixels = [255, 0, 0, 255];
+        FilterMode::Posterize(posterize).apply_cpu(&mut pixels, 1, 1);
+        assert_eq!(pixels, [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn apply_cpu_grayscale() {
+        let mut pixels = [255, 0, 0, 10, 255, 255, 255, 255, 0, 0, 0, 0];
+        FilterMode::LabGrayscale.apply_cpu(&mut pixels, 3, 1);
+        assert_eq!(
+            pixels,
+            [136, 136, 136, 255, 255, 255, 255, 255, 0, 0, 0, 255]
+        );
+
+        let mut pixels = [0, 0, 255, 0];
+        FilterMode::OklabGrayscale.apply_cpu(&mut pixels, 1, 1);
+        let expected = unorm(toe(0.452014));
+        assert_eq!(pixels, [expected, expected, expected, 255]);
+    }
+
+    #[test]
+    fn pipeline_order() {
+        let protan_then_gray = [
+            FilterMode::Protanopia { severity: 1.0 },
+            FilterMode::LabGrayscale,
+        ];
+        let mut pixels = [255, 0, 0, 255];
+        apply_pipeline(&protan_then_gray, &mut pixels, 1, 1);
+
+        let mut expected = [255, 0, 0, 255];
+        protan_then_gray[0].apply_cpu(&mut expected, 1, 1);
+        protan_then_gray[1].apply_cpu(&mut expected, 1, 1);
+        assert_eq!(pixels, expected);
+
+        // Grays are left alone by the simulation, so the other order is just grayscale.
+        let mut pixels = [255, 0, 0, 255];
+        apply_pipeline(
+            &[
+                FilterMode::LabGrayscale,
+                FilterMode::Protanopia { severity: 1.0 },
+            ],
+            &mut pixels,
+            1,
+            1,
+        );
+        assert_eq!(pixels, [136, 136, 136, 255]);
+        assert_ne!(pixels, expected);
+    }
+
+    #[test]
+    fn heatmap_gradient() {
+        let heatmap = Heatmap {
+            lightness: Lightness::Lab,
+            colors: vec![[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+            legend: true,
+        };
+        assert_eq!(gradient(&heatmap.colors, 0.0), [0.0, 0.0, 1.0]);
+        assert_eq!(gradient(&heatmap.colors, 0.25), [0.5, 0.0, 0.5]);
+        assert_eq!(gradient(&heatmap.colors, 0.75), [1.0, 0.5, 0.0]);
+        assert_eq!(gradient(&heatmap.colors, 1.5), [1.0, 1.0, 0.0]);
+
+        assert_eq!(Heatmap::legend_value(0.5, 0.5, 1920.0, 1080.0), None);
+        assert_eq!(
+            Heatmap::legend_value(1890.0, 1080.0 - 24.0 - 128.0, 1920.0, 1080.0),
+            Some(0.5)
+        );
+
+        // Black, with the legend over the bottom right.
+        let (width, height) = (64, 300);
+        let mut pixels = [0, 0, 0, 255].repeat(width * height);
+        FilterMode::Heatmap(heatmap).apply_cpu(&mut pixels, width as u32, height as u32);
+        let at = |x: usize, y: usize| &pixels[(y * width + x) * 4..][..4];
+        assert_eq!(at(0, 0), [0, 0, 255, 255]);
+        assert_eq!(at(30, height - 25), [1, 0, 254, 255]);
+        assert_eq!(at(30, height - 24), [0, 0, 255, 255]);
+        assert_eq!(at(30, 20), [255, 254, 0, 255]);
+    }
+
+    #[test]
+    fn chroma() {
+        // Grays have none, blue is the most saturated sRGB color in both.
+        for [r, g, b] in [[0.0; 3], [0.2; 3], [1.0; 3]] {
+            assert_close(oklch_chroma(r, g, b), 0.0, 1e-4);
+            assert_close(lab_chroma(r, g, b), 0.0, 1e-2);
+        }
+        assert_close(oklch_chroma(0.0, 0.0, 1.0), 0.3132, 1e-3);
+        assert_close(lab_chroma(0.0, 0.0, 1.0), 133.8, 0.2);
+        for c in [
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [0.0, 1.0, 1.0],
+        ] {
+            assert!(oklch_chroma(c[0], c[1], c[2]) < OKLCH_MAX_CHROMA);
+            assert!(lab_chroma(c[0], c[1], c[2]) < LAB_MAX_CHROMA);
+        }
+
+        let mut chroma = Chroma {
+            model: Lightness::Oklab,
+            colors: vec![[0.0; 3], [1.0; 3]],
+            clip: None,
+            clip_color: [1.0, 0.0, 1.0],
+        };
+        let gray = FilterMode::Chroma(chroma.clone()).apply_color([0.5; 3]);
+        assert_close(gray[0], 0.0, 1e-4);
+        let red = FilterMode::Chroma(chroma.clone()).apply_color([1.0, 0.0, 0.0]);
+        assert_close(red[0], 0.2577 / OKLCH_MAX_CHROMA, 1e-3);
+
+        chroma.clip = Some(0.2);
+        let mode = FilterMode::Chroma(chroma);
+        assert_eq!(mode.apply_color([1.0, 0.0, 0.0]), [1.0, 0.0, 1.0]);
+        assert_ne!(mode.apply_color([0.6, 0.5, 0.5]), [1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn hue_weight() {
+        let hue = Hue {
+            center: 350.0,
+            width: 40.0,
+            falloff: 10.0,
+        };
+        assert_eq!(hue.weight(350.0), 1.0);
+        assert_eq!(hue.weight(-20.0), 1.0);
+        assert_eq!(hue.weight(10.0), 1.0);
+        assert_eq!(hue.weight(15.0), 0.5);
+        assert_eq!(hue.weight(20.0), 0.0);
+        assert_eq!(hue.weight(170.0), 0.0);
+        let hard = Hue {
+            falloff: 0.0,
+            ..hue
+        };
+        assert_eq!(hard.weight(10.0), 1.0);
+        assert_eq!(hard.weight(10.5), 0.0);
+
+        // Red sits around 29 degrees, green around 142.
+        let mode = FilterMode::Hue(Hue {
+            center: 30.0,
+            width: 20.0,
+            falloff: 10.0,
+        });
+        assert_eq!(mode.apply_color([1.0, 0.0, 0.0]), [1.0, 0.0, 0.0]);
+        let [r, g, b] = mode.apply_color([0.0, 1.0, 0.0]);
+        assert_eq!(r, g);
+        assert_eq!(g, b);
+        assert_close(
+            r,
+            FilterMode::OklabGrayscale.apply_color([0.0, 1.0, 0.0])[0],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn clipping() {
+        let mut clipping = Clipping {
+            shadows: 8,
+            highlights: 250,
+            channels: true,
+            style: ClippingStyle::Hatch,
+            shadow_color: [0.0, 0.0, 1.0],
+            highlight_color: [1.0, 0.0, 0.0],
+            channel_color: [1.0, 1.0, 0.0],
+        };
+        let value = |v: u8| v as f32 / 255.0;
+        assert_eq!(clipping.warning([value(8); 3]), Some([0.0, 0.0, 1.0]));
+        assert_eq!(clipping.warning([value(250); 3]), Some([1.0, 0.0, 0.0]));
+        assert_eq!(
+            clipping.warning([value(100), value(255), value(100)]),
+            Some([1.0, 1.0, 0.0])
+        );
+        assert_eq!(clipping.warning([value(9), value(128), value(249)]), None);
+
+        // Stripes along the diagonal, the rest keeps its color.
+        let color = [0.0; 3];
+        assert_eq!(clipping.shade(color, 0, 0), [0.0, 0.0, 1.0]);
+        assert_eq!(clipping.shade(color, 1, 2), [0.0, 0.0, 1.0]);
+        assert_eq!(clipping.shade(color, 2, 2), color);
+        assert_eq!(clipping.shade(color, 8, 0), [0.0, 0.0, 1.0]);
+        assert_eq!(clipping.shade([0.5; 3], 0, 0), [0.5; 3]);
+
+        clipping.style = ClippingStyle::Tint;
+        assert_eq!(clipping.shade(color, 2, 2), [0.0, 0.0, 0.5]);
+
+        clipping.channels = false;
+        let mut pixels = [100, 255, 100, 255, 0, 0, 0, 255];
+        FilterMode::Clipping(clipping).apply_cpu(&mut pixels, 2, 1);
+        assert_eq!(pixels, [100, 255, 100, 255, 0, 0, 128, 255]);
+    }
+
+    #[test]
+    fn contrast() {
+        let [black, white] = [[0.0; 3], [1.0; 3]];
+        assert_close(contrast_ratio(relative_luminance(white), 0.0), 21.0, 1e-3);
+        assert_close(contrast_ratio(0.0, relative_luminance(white)), 21.0, 1e-3);
+        // #767676 is the lightest gray passing AA on white.
+        let gray = [0x76 as f32 / 255.0; 3];
+        assert_close(
+            contrast_ratio(relative_luminance(gray), relative_luminance(white)),
+            4.54,
+            1e-2,
+        );
+
+        // Reference values of the APCA 0.0.98G calculator.
+        assert_close(apca_contrast(black, white), 106.04, 0.05);
+        assert_close(apca_contrast(white, black), -107.88, 0.05);
+        assert_close(apca_contrast(gray, white), 71.57, 0.05);
+        assert_eq!(apca_contrast(gray, gray), 0.0);
+
+        let wcag = Contrast {
+            metric: ContrastMetric::Wcag,
+            background: black,
+        };
+        assert_eq!(wcag.level(black), 0.0);
+        assert_close(wcag.level(white), 1.0, 1e-4);
+        let apca = Contrast {
+            metric: ContrastMetric::Apca,
+            background: white,
+        };
+        assert_close(apca.level(black), 106.04 / APCA_MAX, 1e-3);
+        assert_eq!(apca.level(white), 0.0);
+    }
+
+    #[test]
+    fn edges() {
+        let edges = Edges {
+            lightness: Lightness::Lab,
+            threshold: 0.5,
+            thickness: 1,
+            invert: false,
+            overlay: false,
+        };
+        // Black on the left, white on the right.
+        let step = [0.0, 0.0, 1.0, 1.0].repeat(3);
+        assert_eq!(edges.strength(&step, 4, 0, 1), 0.0);
+        assert_eq!(edges.strength(&step, 4, 1, 1), 1.0);
+        assert_eq!(edges.strength(&step, 4, 2, 1), 1.0);
+        assert_eq!(edges.strength(&step, 4, 3, 0), 0.0);
+        let thick = Edges {
+            thickness: 2,
+            ..edges
+        };
+        assert_eq!(thick.strength(&step, 4, 0, 1), 1.0);
+
+        let image = |pixels: [u8; 4]| {
+            pixels
+                .iter()
+                .flat_map(|&v| [v, v, v, 255])
+                .collect::<Vec<u8>>()
+        };
+        let run = |edges: Edges, input: [u8; 4]| {
+            let mut pixels = image(input);
+            FilterMode::Edges(edges).apply_cpu(&mut pixels, 4, 1);
+            pixels.chunks(4).map(|px| px[0]).collect::<Vec<_>>()
+        };
+        assert_eq!(run(edges, [0, 0, 255, 255]), [255, 0, 0, 255]);
+        let inverted = Edges {
+            invert: true,
+            ..edges
+        };
+        assert_eq!(run(inverted, [0, 0, 255, 255]), [0, 255, 255, 0]);
+        let overlay = Edges {
+            overlay: true,
+            ..edges
+        };
+        assert_eq!(run(overlay, [40, 40, 255, 255]), [40, 0, 0, 255]);
+    }
+
+    #[test]
+    fn blur_kernel() {
+        let blur = Blur { radius: 3.0 };
+        assert_eq!(blur.sigma(), 1.0);
+        assert_eq!(Blur { radius: 2.5 }.taps(), 3);
+
+        // Flat colors stay the same, the edges are clamped.
+        let mut pixels = [200, 100, 50, 0].repeat(12);
+        FilterMode::Blur(blur).apply_cpu(&mut pixels, 4, 3);
+        assert_eq!(pixels, [200, 100, 50, 255].repeat(12));
+
+        // A white dot spreads symmetrically and loses its peak.
+        let mut pixels = [0, 0, 0, 255].repeat(25);
+        pixels[12 * 4..12 * 4 + 3].copy_from_slice(&[255; 3]);
+        FilterMode::Blur(blur).apply_cpu(&mut pixels, 5, 5);
+        let at = |x: usize, y: usize| pixels[(y * 5 + x) * 4];
+        assert!(at(2, 2) < 255);
+        assert!(at(2, 2) > at(1, 2) && at(1, 2) > at(0, 2) && at(0, 2) > 0);
+        assert_eq!(at(1, 2), at(3, 2));
+        assert_eq!(at(2, 1), at(2, 3));
+        // Only off by the rounding in between the passes.
+        assert!(at(1, 2).abs_diff(at(2, 1)) <= 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_cpu_size_mismatch() {
+        FilterMode::LabGrayscale.apply_cpu(&mut [0; 8], 3, 1);
+    }
+}
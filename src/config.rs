@@ -1,31 +1,1129 @@
-use std::{sync::mpsc::Receiver, time::Duration};
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc::Receiver},
+    time::Duration,
+};
 
 use notify::RecursiveMode;
 use notify_debouncer_mini::new_debouncer;
-use serde::Deserialize;
-use win_hotkey::keys::{ModifiersKey, VirtualKey};
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+use crate::lut::{Cube, Lut};
+#[cfg(windows)]
+use {
+    std::borrow::Cow,
+    win_hotkey::keys::{ModifiersKey, VirtualKey},
+};
+
+#[derive(Deserialize, Debug, Clone)]
 pub enum FilterMode {
     OklabGrayscale,
     LabGrayscale,
+    /// WCAG luminance or APCA contrast as grayscale.
+    Contrast(Contrast),
+    Protanopia {
+        #[serde(default = "full_severity", deserialize_with = "severity")]
+        severity: f32,
+    },
+    Deuteranopia {
+        #[serde(default = "full_severity", deserialize_with = "severity")]
+        severity: f32,
+    },
+    Tritanopia {
+        #[serde(default = "full_severity", deserialize_with = "severity")]
+        severity: f32,
+    },
+    Achromatopsia {
+        #[serde(default = "full_severity", deserialize_with = "severity")]
+        severity: f32,
+    },
+    Posterize(Posterize),
+    /// Gaussian blur, like squinting at the screen.
+    Blur(Blur),
+    /// Lines where the lightness changes, like an outline drawing.
+    Edges(Edges),
+    /// Lightness mapped onto a color gradient.
+    Heatmap(Heatmap),
+    /// Chroma as grayscale or mapped onto a color gradient.
+    Chroma(Chroma),
+    /// A range of hues in color, everything else in grayscale.
+    Hue(Hue),
+    /// Marks crushed shadows, blown highlights and clipped channels on top of
+    /// the colors.
+    Clipping(Clipping),
+    /// A user HLSL file, relative to the config file.
+    Custom(PathBuf),
+    /// A `.cube` 3D LUT applied to the sRGB colors.
+    Lut(Lut),
+}
+
+impl FilterMode {
+    /// Custom shaders only run on the GPU, so they can't be used where the
+    /// filter is applied on the CPU.
+    pub fn supports_cpu(&self) -> bool {
+        !matches!(self, FilterMode::Custom(_))
+    }
+
+    /// Makes paths relative to `dir` and loads the files the filter needs.
+    pub fn resolve(&mut self, dir: &Path) -> Result<(), String> {
+        match self {
+            FilterMode::Custom(path) => {
+                *path = dir.join(&path);
+                // Compiled by the overlay, but a missing file is reported here.
+                std::fs::read_to_string(&path)
+                    .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+            }
+            FilterMode::Lut(lut) => {
+                lut.path = dir.join(&lut.path);
+                lut.cube = Some(Arc::new(Cube::load(&lut.path)?));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The file the filter is read from, if any.
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            FilterMode::Custom(path) => Some(path),
+            FilterMode::Lut(lut) => Some(&lut.path),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterMode::OklabGrayscale => "OklabGrayscale",
+            FilterMode::LabGrayscale => "LabGrayscale",
+            FilterMode::Contrast(_) => "Contrast",
+            FilterMode::Protanopia { .. } => "Protanopia",
+            FilterMode::Deuteranopia { .. } => "Deuteranopia",
+            FilterMode::Tritanopia { .. } => "Tritanopia",
+            FilterMode::Achromatopsia { .. } => "Achromatopsia",
+            FilterMode::Posterize(_) => "Posterize",
+            FilterMode::Blur(_) => "Blur",
+            FilterMode::Edges(_) => "Edges",
+            FilterMode::Heatmap(_) => "Heatmap",
+            FilterMode::Chroma(_) => "Chroma",
+            FilterMode::Hue(_) => "Hue",
+            FilterMode::Clipping(_) => "Clipping",
+            FilterMode::Custom(_) => "Custom",
+            FilterMode::Lut(_) => "Lut",
+        }
+    }
+
+    /// Every mode that doesn't need a file, with its default options, cycled
+    /// through when there are no profiles.
+    pub fn presets() -> Vec<FilterMode> {
+        #[derive(Deserialize)]
+        struct Presets {
+            modes: Vec<FilterMode>,
+        }
+        toml::from_str::<Presets>(PRESETS).unwrap().modes
+    }
+}
+
+const PRESETS: &str = r#"modes = [
+    "OklabGrayscale",
+    "LabGrayscale",
+    { Contrast = {} },
+    { Protanopia = {} },
+    { Deuteranopia = {} },
+    { Tritanopia = {} },
+    { Achromatopsia = {} },
+    { Posterize = {} },
+    { Blur = {} },
+    { Edges = {} },
+    { Heatmap = {} },
+    { Chroma = {} },
+    { Hue = { center = 140 } },
+    { Clipping = {} },
+]"#;
+
+fn full_severity() -> f32 {
+    1.0
+}
+
+fn severity<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let severity = f32::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&severity) {
+        return Err(serde::de::Error::custom(format!(
+            "Severity must be between 0 and 1, got {}",
+            severity
+        )));
+    }
+    Ok(severity)
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lightness {
+    #[default]
+    Oklab,
+    Lab,
+}
+
+/// Largest blur radius, in pixels.
+pub const MAX_BLUR_RADIUS: f32 = 64.0;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Blur {
+    /// Pixels the blur reaches out to, three standard deviations.
+    #[serde(default = "default_blur_radius", deserialize_with = "blur_radius")]
+    pub radius: f32,
+}
+
+fn default_blur_radius() -> f32 {
+    8.0
+}
+
+fn blur_radius<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let radius = f32::deserialize(deserializer)?;
+    if !(radius > 0.0 && radius <= MAX_BLUR_RADIUS) {
+        return Err(serde::de::Error::custom(format!(
+            "Blur radius must be above 0 and at most {}, got {}",
+            MAX_BLUR_RADIUS, radius
+        )));
+    }
+    Ok(radius)
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContrastMetric {
+    /// WCAG 2.x contrast ratio.
+    #[default]
+    Wcag,
+    /// APCA lightness contrast `Lc`.
+    Apca,
+}
+
+/// Shows the contrast of every pixel against a background color, brighter
+/// meaning more contrast.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "ContrastOptions")]
+pub struct Contrast {
+    pub metric: ContrastMetric,
+    /// sRGB color, black by default for WCAG, which shows the relative
+    /// luminance itself, and white for APCA, taking every pixel as text.
+    pub background: [f32; 3],
+}
+
+/// [`Contrast`] as written in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContrastOptions {
+    #[serde(default)]
+    metric: ContrastMetric,
+    background: Option<String>,
+}
+
+impl TryFrom<ContrastOptions> for Contrast {
+    type Error = String;
+
+    fn try_from(options: ContrastOptions) -> Result<Self, Self::Error> {
+        let background = match (options.background, options.metric) {
+            (Some(c), _) => parse_color(&c).ok_or_else(|| format!("Invalid color: {}", c))?,
+            (None, ContrastMetric::Wcag) => [0.0; 3],
+            (None, ContrastMetric::Apca) => [1.0; 3],
+        };
+        Ok(Contrast {
+            metric: options.metric,
+            background,
+        })
+    }
+}
+
+/// Degrees the hue hotkeys rotate or widen the range by.
+pub const HUE_STEP: f32 = 10.0;
+
+/// Keeps the colors within a range of OkLCh hues and shows the rest as their
+/// OkLab lightness.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Hue {
+    /// OkLCh hue in degrees the range is centered on.
+    #[serde(deserialize_with = "hue_center")]
+    pub center: f32,
+    /// Degrees the range spans.
+    #[serde(default = "default_hue_width", deserialize_with = "hue_width")]
+    pub width: f32,
+    /// Degrees outside of the range over which colors fade to gray.
+    #[serde(default = "default_hue_falloff", deserialize_with = "hue_falloff")]
+    pub falloff: f32,
 }
 
+impl Hue {
+    /// The range rotated by `shift` and widened by `widen` degrees, like the
+    /// hotkeys do.
+    pub fn adjusted(&self, shift: f32, widen: f32) -> Self {
+        Self {
+            center: (self.center + shift).rem_euclid(360.0),
+            width: (self.width + widen).clamp(HUE_STEP, 360.0),
+            falloff: self.falloff,
+        }
+    }
+}
+
+fn default_hue_width() -> f32 {
+    60.0
+}
+
+fn default_hue_falloff() -> f32 {
+    15.0
+}
+
+fn hue_center<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(f32::deserialize(deserializer)?.rem_euclid(360.0))
+}
+
+fn hue_width<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let width = f32::deserialize(deserializer)?;
+    if !(width > 0.0 && width <= 360.0) {
+        return Err(serde::de::Error::custom(format!(
+            "Hue width must be above 0 and at most 360, got {}",
+            width
+        )));
+    }
+    Ok(width)
+}
+
+fn hue_falloff<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let falloff = f32::deserialize(deserializer)?;
+    if !(0.0..=180.0).contains(&falloff) {
+        return Err(serde::de::Error::custom(format!(
+            "Hue falloff must be between 0 and 180, got {}",
+            falloff
+        )));
+    }
+    Ok(falloff)
+}
+
+/// Widest edge lines, in pixels between the samples.
+pub const MAX_EDGE_THICKNESS: u32 = 8;
+
+/// Sobel edge detection on lightness.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Edges {
+    #[serde(default)]
+    pub lightness: Lightness,
+    /// Gradient strength, 1 for a black to white step, from which a pixel is
+    /// part of a line.
+    #[serde(
+        default = "default_edge_threshold",
+        deserialize_with = "edge_threshold"
+    )]
+    pub threshold: f32,
+    /// Pixels between the samples, thicker lines for higher values.
+    #[serde(
+        default = "default_edge_thickness",
+        deserialize_with = "edge_thickness"
+    )]
+    pub thickness: u32,
+    /// White lines on black instead of black lines on white.
+    #[serde(default)]
+    pub invert: bool,
+    /// Draws the lines over the input instead of a blank background.
+    #[serde(default)]
+    pub overlay: bool,
+}
+
+fn default_edge_threshold() -> f32 {
+    0.1
+}
+
+fn default_edge_thickness() -> u32 {
+    1
+}
+
+fn edge_threshold<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let threshold = f32::deserialize(deserializer)?;
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        return Err(serde::de::Error::custom(format!(
+            "Edge threshold must be above 0 and at most 1, got {}",
+            threshold
+        )));
+    }
+    Ok(threshold)
+}
+
+fn edge_thickness<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let thickness = u32::deserialize(deserializer)?;
+    if !(1..=MAX_EDGE_THICKNESS).contains(&thickness) {
+        return Err(serde::de::Error::custom(format!(
+            "Edge thickness must be between 1 and {}, got {}",
+            MAX_EDGE_THICKNESS, thickness
+        )));
+    }
+    Ok(thickness)
+}
+
+/// Most bands the shader is compiled for.
+pub const MAX_BANDS: usize = 16;
+
+/// Quantizes lightness into bands, like a notan study.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "PosterizeOptions")]
+pub struct Posterize {
+    pub lightness: Lightness,
+    /// Lightness where each band but the first starts, ascending.
+    pub breakpoints: Vec<f32>,
+    /// sRGB color each band is shown with, channels in `0..=1`.
+    pub colors: Vec<[f32; 3]>,
+}
+
+/// [`Posterize`] as written in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PosterizeOptions {
+    #[serde(default)]
+    lightness: Lightness,
+    bands: Option<usize>,
+    breakpoints: Option<Vec<f32>>,
+    colors: Option<Vec<String>>,
+}
+
+impl TryFrom<PosterizeOptions> for Posterize {
+    type Error = String;
+
+    fn try_from(options: PosterizeOptions) -> Result<Self, Self::Error> {
+        let check = |bands: usize| {
+            if (2..=MAX_BANDS).contains(&bands) {
+                Ok(bands)
+            } else {
+                Err(format!(
+                    "Band count must be between 2 and {}, got {}",
+                    MAX_BANDS, bands
+                ))
+            }
+        };
+        let bands = match (&options.breakpoints, options.bands) {
+            (Some(breakpoints), Some(bands)) => {
+                let bands = check(bands)?;
+                if breakpoints.len() + 1 != bands {
+                    return Err(format!(
+                        "Expected {} breakpoints for {} bands, got {}",
+                        bands - 1,
+                        bands,
+                        breakpoints.len()
+                    ));
+                }
+                bands
+            }
+            (Some(breakpoints), None) => check(breakpoints.len() + 1)?,
+            (None, bands) => check(bands.unwrap_or(3))?,
+        };
+
+        // Equal steps by default.
+        let breakpoints = options
+            .breakpoints
+            .unwrap_or_else(|| (1..bands).map(|i| i as f32 / bands as f32).collect());
+        let ascending = breakpoints.windows(2).all(|w| w[0] < w[1]);
+        if !ascending || breakpoints.iter().any(|b| !(0.0..=1.0).contains(b)) {
+            return Err("Breakpoints must be ascending and between 0 and 1".into());
+        }
+
+        // Black to white by default.
+        let colors = match options.colors {
+            Some(colors) if colors.len() != bands => {
+                return Err(format!("Expected {} colors, got {}", bands, colors.len()));
+            }
+            Some(colors) => colors
+                .iter()
+                .map(|c| parse_color(c).ok_or_else(|| format!("Invalid color: {}", c)))
+                .collect::<Result<_, _>>()?,
+            None => (0..bands)
+                .map(|i| [i as f32 / (bands - 1) as f32; 3])
+                .collect(),
+        };
+
+        Ok(Posterize {
+            lightness: options.lightness,
+            breakpoints,
+            colors,
+        })
+    }
+}
+
+/// Most gradient stops the heatmap shader is compiled for.
+pub const MAX_STOPS: usize = 16;
+
+/// Viridis from matplotlib.
+const VIRIDIS: [&str; 9] = [
+    "#440154", "#472d7b", "#3b528b", "#2c728e", "#21918c", "#28ae80", "#5ec962", "#addc30",
+    "#fde725",
+];
+/// Turbo, sampled from Google's polynomial approximation.
+const TURBO: [&str; 11] = [
+    "#23171b", "#4958dd", "#2f9ef5", "#27d7c3", "#4ef983", "#96fa50", "#dfdc32", "#ffa323",
+    "#f45c17", "#b82008", "#900d00",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    Viridis,
+    Turbo,
+}
+
+impl Colormap {
+    fn colors(self) -> Vec<[f32; 3]> {
+        let stops: &[&str] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Turbo => &TURBO,
+        };
+        stops.iter().map(|c| parse_color(c).unwrap()).collect()
+    }
+}
+
+/// Gradient stops of either a colormap or user colors, `None` if neither is
+/// set.
+fn gradient(
+    colormap: Option<Colormap>,
+    colors: Option<Vec<String>>,
+) -> Result<Option<Vec<[f32; 3]>>, String> {
+    match (colormap, colors) {
+        (Some(_), Some(_)) => Err("Set either colormap or colors, not both".into()),
+        (_, Some(colors)) if !(2..=MAX_STOPS).contains(&colors.len()) => Err(format!(
+            "Expected 2 to {} colors, got {}",
+            MAX_STOPS,
+            colors.len()
+        )),
+        (_, Some(colors)) => colors
+            .iter()
+            .map(|c| parse_color(c).ok_or_else(|| format!("Invalid color: {}", c)))
+            .collect::<Result<_, _>>()
+            .map(Some),
+        (colormap, None) => Ok(colormap.map(Colormap::colors)),
+    }
+}
+
+/// Maps lightness onto a gradient, so small differences stand out.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "HeatmapOptions")]
+pub struct Heatmap {
+    pub lightness: Lightness,
+    /// sRGB gradient stops, evenly spaced from black to white.
+    pub colors: Vec<[f32; 3]>,
+    /// Shows the gradient in the bottom right corner of each output.
+    pub legend: bool,
+}
+
+/// [`Heatmap`] as written in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeatmapOptions {
+    #[serde(default)]
+    lightness: Lightness,
+    colormap: Option<Colormap>,
+    colors: Option<Vec<String>>,
+    #[serde(default)]
+    legend: bool,
+}
+
+impl TryFrom<HeatmapOptions> for Heatmap {
+    type Error = String;
+
+    fn try_from(options: HeatmapOptions) -> Result<Self, Self::Error> {
+        let colors = gradient(options.colormap, options.colors)?
+            .unwrap_or_else(|| Colormap::Viridis.colors());
+
+        Ok(Heatmap {
+            lightness: options.lightness,
+            colors,
+            legend: options.legend,
+        })
+    }
+}
+
+/// Shows how saturated colors are, optionally highlighting the ones above a
+/// threshold.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "ChromaOptions")]
+pub struct Chroma {
+    /// OkLCh or CIE LCh.
+    pub model: Lightness,
+    /// sRGB gradient stops, evenly spaced from no chroma to the most saturated
+    /// sRGB colors.
+    pub colors: Vec<[f32; 3]>,
+    /// Chroma above which colors are shown as `clip_color`, like 0.2 for OkLCh
+    /// or 80 for CIE LCh.
+    pub clip: Option<f32>,
+    pub clip_color: [f32; 3],
+}
+
+/// [`Chroma`] as written in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChromaOptions {
+    #[serde(default)]
+    model: Lightness,
+    colormap: Option<Colormap>,
+    colors: Option<Vec<String>>,
+    clip: Option<f32>,
+    clip_color: Option<String>,
+}
+
+impl TryFrom<ChromaOptions> for Chroma {
+    type Error = String;
+
+    fn try_from(options: ChromaOptions) -> Result<Self, Self::Error> {
+        // Black to white by default.
+        let colors =
+            gradient(options.colormap, options.colors)?.unwrap_or_else(|| vec![[0.0; 3], [1.0; 3]]);
+        if options
+            .clip
+            .is_some_and(|clip| !clip.is_finite() || clip <= 0.0)
+        {
+            return Err("Clip threshold must be finite and above 0".into());
+        }
+        let clip_color = match options.clip_color {
+            Some(c) => parse_color(&c).ok_or_else(|| format!("Invalid color: {}", c))?,
+            None => [1.0, 0.0, 1.0],
+        };
+
+        Ok(Chroma {
+            model: options.model,
+            colors,
+            clip: options.clip,
+            clip_color,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClippingStyle {
+    /// Diagonal stripes of the warning color.
+    #[default]
+    Hatch,
+    /// The warning color blended halfway in.
+    Tint,
+}
+
+/// Marks pixels at or beyond the thresholds, everything else is left alone.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "ClippingOptions")]
+pub struct Clipping {
+    /// 8-bit sRGB value at or below which a channel counts as clipped.
+    pub shadows: u8,
+    /// 8-bit sRGB value at or above which a channel counts as clipped.
+    pub highlights: u8,
+    /// Also mark pixels with only some channels clipped.
+    pub channels: bool,
+    pub style: ClippingStyle,
+    pub shadow_color: [f32; 3],
+    pub highlight_color: [f32; 3],
+    pub channel_color: [f32; 3],
+}
+
+/// [`Clipping`] as written in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClippingOptions {
+    #[serde(default)]
+    shadows: u8,
+    #[serde(default = "default_highlights")]
+    highlights: u8,
+    #[serde(default = "default_channels")]
+    channels: bool,
+    #[serde(default)]
+    style: ClippingStyle,
+    shadow_color: Option<String>,
+    highlight_color: Option<String>,
+    channel_color: Option<String>,
+}
+
+fn default_highlights() -> u8 {
+    255
+}
+
+fn default_channels() -> bool {
+    true
+}
+
+impl TryFrom<ClippingOptions> for Clipping {
+    type Error = String;
+
+    fn try_from(options: ClippingOptions) -> Result<Self, Self::Error> {
+        if options.shadows >= options.highlights {
+            return Err(format!(
+                "Shadows must be below highlights, got {} and {}",
+                options.shadows, options.highlights
+            ));
+        }
+        let color = |color: Option<String>, default: [f32; 3]| match color {
+            Some(c) => parse_color(&c).ok_or_else(|| format!("Invalid color: {}", c)),
+            None => Ok(default),
+        };
+
+        Ok(Clipping {
+            shadows: options.shadows,
+            highlights: options.highlights,
+            channels: options.channels,
+            style: options.style,
+            shadow_color: color(options.shadow_color, [0.0, 0.5, 1.0])?,
+            highlight_color: color(options.highlight_color, [1.0, 0.0, 0.0])?,
+            channel_color: color(options.channel_color, [1.0, 1.0, 0.0])?,
+        })
+    }
+}
+
+/// Parses a `#rrggbb` color.
+fn parse_color(hex: &str) -> Option<[f32; 3]> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|c| c as f32 / 255.0)
+    };
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(windows)]
 const OKLAB_GRAYSCALE_FRAGMENT_SHADER: &str = include_str!("./shaders/oklab_grayscale.hlsl");
+#[cfg(windows)]
 const LAB_GRAYSCALE_FRAGMENT_SHADER: &str = include_str!("./shaders/lab_grayscale.hlsl");
+#[cfg(windows)]
+const CONTRAST_FRAGMENT_SHADER: &str = include_str!("./shaders/contrast.hlsl");
+#[cfg(windows)]
+const CVD_FRAGMENT_SHADER: &str = include_str!("./shaders/cvd.hlsl");
+#[cfg(windows)]
+const POSTERIZE_FRAGMENT_SHADER: &str = include_str!("./shaders/posterize.hlsl");
+#[cfg(windows)]
+const LUT_FRAGMENT_SHADER: &str = include_str!("./shaders/lut.hlsl");
+#[cfg(windows)]
+const BLUR_FRAGMENT_SHADER: &str = include_str!("./shaders/blur.hlsl");
+#[cfg(windows)]
+const EDGES_FRAGMENT_SHADER: &str = include_str!("./shaders/edges.hlsl");
+#[cfg(windows)]
+const HEATMAP_FRAGMENT_SHADER: &str = include_str!("./shaders/heatmap.hlsl");
+#[cfg(windows)]
+const CHROMA_FRAGMENT_SHADER: &str = include_str!("./shaders/chroma.hlsl");
+#[cfg(windows)]
+const HUE_FRAGMENT_SHADER: &str = include_str!("./shaders/hue.hlsl");
+#[cfg(windows)]
+const CLIPPING_FRAGMENT_SHADER: &str = include_str!("./shaders/clipping.hlsl");
 
+#[cfg(windows)]
 impl FilterMode {
-    pub fn fragment_shader(&self) -> &'static str {
-        match self {
+    pub fn fragment_shader(&self) -> std::io::Result<Cow<'static, str>> {
+        Ok(Cow::Borrowed(match self {
             FilterMode::OklabGrayscale => OKLAB_GRAYSCALE_FRAGMENT_SHADER,
             FilterMode::LabGrayscale => LAB_GRAYSCALE_FRAGMENT_SHADER,
+            FilterMode::Contrast(_) => CONTRAST_FRAGMENT_SHADER,
+            FilterMode::Protanopia { .. }
+            | FilterMode::Deuteranopia { .. }
+            | FilterMode::Tritanopia { .. }
+            | FilterMode::Achromatopsia { .. } => CVD_FRAGMENT_SHADER,
+            FilterMode::Posterize(_) => POSTERIZE_FRAGMENT_SHADER,
+            FilterMode::Lut(_) => LUT_FRAGMENT_SHADER,
+            FilterMode::Blur(_) => BLUR_FRAGMENT_SHADER,
+            FilterMode::Edges(_) => EDGES_FRAGMENT_SHADER,
+            FilterMode::Heatmap(_) => HEATMAP_FRAGMENT_SHADER,
+            FilterMode::Chroma(_) => CHROMA_FRAGMENT_SHADER,
+            FilterMode::Hue(_) => HUE_FRAGMENT_SHADER,
+            FilterMode::Clipping(_) => CLIPPING_FRAGMENT_SHADER,
+            FilterMode::Custom(path) => return std::fs::read_to_string(path).map(Cow::Owned),
+        }))
+    }
+}
+
+/// Mirrors the filtered image of each output in place.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Transform {
+    #[default]
+    None,
+    FlipHorizontal,
+    FlipVertical,
+    Rotate180,
+}
+
+/// A key that can be bound, named after its label on a US keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// An uppercase ASCII letter or digit.
+    Char(char),
+    /// `F1` to `F24`.
+    Function(u8),
+    /// `Numpad0` to `Numpad9`.
+    Numpad(u8),
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    Escape,
+    Tab,
+    Space,
+    Enter,
+    Backspace,
+    Pause,
+    PrintScreen,
+    ScrollLock,
+    CapsLock,
+    NumLock,
+    Semicolon,
+    Equals,
+    /// The `+` key of the layout, `=` on a US one.
+    Plus,
+    Comma,
+    Minus,
+    Period,
+    Slash,
+    Backquote,
+    LeftBracket,
+    Backslash,
+    RightBracket,
+    Quote,
+}
+
+/// Names of the keys besides letters, digits, function keys and numpad
+/// digits, matched case-insensitively. The first one is displayed.
+const KEY_NAMES: &[(Key, &[&str])] = &[
+    (Key::NumpadAdd, &["NumpadAdd", "NumpadPlus", "Add"]),
+    (
+        Key::NumpadSubtract,
+        &["NumpadSubtract", "NumpadMinus", "Subtract"],
+    ),
+    (Key::NumpadMultiply, &["NumpadMultiply", "Multiply"]),
+    (Key::NumpadDivide, &["NumpadDivide", "Divide"]),
+    (
+        Key::NumpadDecimal,
+        &["NumpadDecimal", "NumpadPeriod", "Decimal"],
+    ),
+    (Key::Up, &["Up", "ArrowUp"]),
+    (Key::Down, &["Down", "ArrowDown"]),
+    (Key::Left, &["Left", "ArrowLeft"]),
+    (Key::Right, &["Right", "ArrowRight"]),
+    (Key::Home, &["Home"]),
+    (Key::End, &["End"]),
+    (Key::PageUp, &["PageUp", "PgUp", "Prior"]),
+    (Key::PageDown, &["PageDown", "PgDn", "Next"]),
+    (Key::Insert, &["Insert", "Ins"]),
+    (Key::Delete, &["Delete", "Del"]),
+    (Key::Escape, &["Escape", "Esc"]),
+    (Key::Tab, &["Tab"]),
+    (Key::Space, &["Space", "Spacebar"]),
+    (Key::Enter, &["Enter", "Return"]),
+    (Key::Backspace, &["Backspace", "Back"]),
+    (Key::Pause, &["Pause", "Break"]),
+    (
+        Key::PrintScreen,
+        &["PrintScreen", "PrtSc", "Print", "Snapshot"],
+    ),
+    (Key::ScrollLock, &["ScrollLock", "Scroll"]),
+    (Key::CapsLock, &["CapsLock", "Caps"]),
+    (Key::NumLock, &["NumLock"]),
+    (Key::Semicolon, &[";", "Semicolon"]),
+    (Key::Equals, &["=", "Equals", "Equal"]),
+    (Key::Plus, &["Plus"]),
+    (Key::Comma, &[",", "Comma"]),
+    (Key::Minus, &["-", "Minus", "Dash"]),
+    (Key::Period, &[".", "Period", "Dot"]),
+    (Key::Slash, &["/", "Slash"]),
+    (
+        Key::Backquote,
+        &["`", "Backquote", "Backtick", "Grave", "Tilde"],
+    ),
+    (Key::LeftBracket, &["[", "LeftBracket", "BracketLeft"]),
+    (Key::Backslash, &["\\", "Backslash"]),
+    (Key::RightBracket, &["]", "RightBracket", "BracketRight"]),
+    (Key::Quote, &["'", "Quote", "Apostrophe"]),
+];
+
+impl Key {
+    /// Whether the key types something, and can't be bound without a
+    /// modifier.
+    fn types_text(self) -> bool {
+        matches!(
+            self,
+            Key::Char(_)
+                | Key::Space
+                | Key::Enter
+                | Key::Tab
+                | Key::Backspace
+                | Key::Semicolon
+                | Key::Equals
+                | Key::Plus
+                | Key::Comma
+                | Key::Minus
+                | Key::Period
+                | Key::Slash
+                | Key::Backquote
+                | Key::LeftBracket
+                | Key::Backslash
+                | Key::RightBracket
+                | Key::Quote
+        )
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        if let [ch] = name.as_bytes()
+            && ch.is_ascii_alphanumeric()
+        {
+            return Some(Key::Char(ch.to_ascii_uppercase() as char));
+        }
+
+        let numbered = |prefix: &str| {
+            let (head, tail) = name.split_at_checked(prefix.len())?;
+            // `parse` would take a sign as well.
+            let digits = head.eq_ignore_ascii_case(prefix)
+                && !tail.is_empty()
+                && tail.bytes().all(|b| b.is_ascii_digit());
+            digits.then(|| tail.parse::<u8>().ok()).flatten()
+        };
+        if let Some(n) = numbered("F").filter(|n| (1..=24).contains(n)) {
+            return Some(Key::Function(n));
+        }
+        if let Some(n) = numbered("Numpad")
+            .or_else(|| numbered("Num"))
+            .filter(|n| *n <= 9)
+        {
+            return Some(Key::Numpad(n));
+        }
+
+        KEY_NAMES
+            .iter()
+            .find(|(_, names)| names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+            .map(|(key, _)| *key)
+    }
+
+    /// The key that is grabbed, `Plus` and `Equals` share one on a US layout.
+    fn physical(self) -> Key {
+        match self {
+            Key::Plus => Key::Equals,
+            key => key,
+        }
+    }
+
+    /// The Windows virtual-key code.
+    #[cfg(windows)]
+    pub fn vk_code(self) -> u16 {
+        match self {
+            // Letters and digits share their codes with ASCII.
+            Key::Char(ch) => ch as u16,
+            Key::Function(n) => 0x6F + n as u16,
+            Key::Numpad(n) => 0x60 + n as u16,
+            Key::NumpadMultiply => 0x6A,
+            Key::NumpadAdd => 0x6B,
+            Key::NumpadSubtract => 0x6D,
+            Key::NumpadDecimal => 0x6E,
+            Key::NumpadDivide => 0x6F,
+            Key::Left => 0x25,
+            Key::Up => 0x26,
+            Key::Right => 0x27,
+            Key::Down => 0x28,
+            Key::PageUp => 0x21,
+            Key::PageDown => 0x22,
+            Key::End => 0x23,
+            Key::Home => 0x24,
+            Key::Insert => 0x2D,
+            Key::Delete => 0x2E,
+            Key::Escape => 0x1B,
+            Key::Tab => 0x09,
+            Key::Space => 0x20,
+            Key::Enter => 0x0D,
+            Key::Backspace => 0x08,
+            Key::Pause => 0x13,
+            Key::PrintScreen => 0x2C,
+            Key::ScrollLock => 0x91,
+            Key::CapsLock => 0x14,
+            Key::NumLock => 0x90,
+            // The OEM keys of a US layout.
+            Key::Semicolon => 0xBA,
+            Key::Equals => 0xBB,
+            // VK_OEM_PLUS
+            Key::Plus => 0xBB,
+            Key::Comma => 0xBC,
+            Key::Minus => 0xBD,
+            Key::Period => 0xBE,
+            Key::Slash => 0xBF,
+            Key::Backquote => 0xC0,
+            Key::LeftBracket => 0xDB,
+            Key::Backslash => 0xDC,
+            Key::RightBracket => 0xDD,
+            Key::Quote => 0xDE,
         }
     }
 }
 
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Char(ch) => write!(f, "{}", ch.to_ascii_lowercase()),
+            Key::Function(n) => write!(f, "F{}", n),
+            Key::Numpad(n) => write!(f, "Numpad{}", n),
+            key => {
+                let (_, names) = KEY_NAMES.iter().find(|(k, _)| k == key).unwrap();
+                f.write_str(names[0])
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Alt,
+    Ctrl,
+    Shift,
+    Win,
+    NoRepeat,
+}
+
+impl Modifier {
+    fn from_keyname(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "ALT" => Modifier::Alt,
+            "CTRL" | "CONTROL" => Modifier::Ctrl,
+            "SHIFT" => Modifier::Shift,
+            "WIN" | "WINDOWS" | "SUPER" => Modifier::Win,
+            "NOREPEAT" | "NO_REPEAT" => Modifier::NoRepeat,
+            _ => return None,
+        })
+    }
+
+    fn keyname(self) -> &'static str {
+        match self {
+            Modifier::Alt => "alt",
+            Modifier::Ctrl => "ctrl",
+            Modifier::Shift => "shift",
+            Modifier::Win => "win",
+            Modifier::NoRepeat => "norepeat",
+        }
+    }
+}
+
+/// Modifiers and a key joined by `+`, e.g. `ctrl+alt+F9` or `ctrl+[`.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeySequence {
-    pub vk: VirtualKey,
-    pub mods: Vec<ModifiersKey>,
+    pub key: Key,
+    pub mods: Vec<Modifier>,
+}
+
+impl KeySequence {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut key = None;
+        let mut mods = Vec::new();
+
+        for token in s.split('+').map(str::trim) {
+            if token.is_empty() {
+                return Err(format!("Empty key in {}", s));
+            }
+            if let Some(m) = Modifier::from_keyname(token) {
+                mods.push(m);
+                continue;
+            }
+            let parsed = Key::from_name(token).ok_or_else(|| format!("Invalid key: {}", token))?;
+            if let Some(first) = key {
+                return Err(format!("Second key {} after {}", token, first));
+            }
+            key = Some(parsed);
+        }
+
+        Ok(KeySequence {
+            key: key.ok_or_else(|| format!("No virtual key found in {}", s))?,
+            mods,
+        })
+    }
+
+    /// Whether both are triggered by the same keys, in any order.
+    fn same_keys(&self, other: &Self) -> bool {
+        self.key.physical() == other.key.physical()
+            && self.held_modifiers() == other.held_modifiers()
+    }
+
+    /// Whether alt, ctrl, shift and win are held, in that order.
+    pub fn held_modifiers(&self) -> [bool; 4] {
+        [
+            Modifier::Alt,
+            Modifier::Ctrl,
+            Modifier::Shift,
+            Modifier::Win,
+        ]
+        .map(|m| self.mods.contains(&m))
+    }
+
+    /// Whether the sequence would swallow typing, like `c` or `shift+c`.
+    fn blocks_typing(&self) -> bool {
+        self.key.types_text()
+            && self
+                .mods
+                .iter()
+                .all(|m| matches!(m, Modifier::Shift | Modifier::NoRepeat))
+    }
+}
+
+#[cfg(windows)]
+impl KeySequence {
+    pub fn virtual_key(&self) -> VirtualKey {
+        VirtualKey::CustomKeyCode(self.key.vk_code())
+    }
+
+    pub fn modifiers(&self) -> Vec<ModifiersKey> {
+        self.mods
+            .iter()
+            .map(|m| match m {
+                Modifier::Alt => ModifiersKey::Alt,
+                Modifier::Ctrl => ModifiersKey::Ctrl,
+                Modifier::Shift => ModifiersKey::Shift,
+                Modifier::Win => ModifiersKey::Win,
+                Modifier::NoRepeat => ModifiersKey::NoRepeat,
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.mods {
+            write!(f, "{}+", m.keyname())?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl Serialize for KeySequence {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
 }
 
 impl<'de> Deserialize<'de> for KeySequence {
@@ -34,40 +1132,451 @@ impl<'de> Deserialize<'de> for KeySequence {
         D: serde::Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        let mut vk = None;
-        let mut mods = Vec::new();
+        KeySequence::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
 
-        for token in s.split('+') {
-            if token.len() == 1 {
-                vk = Some(
-                    VirtualKey::from_char(token.chars().next().unwrap())
-                        .map_err(serde::de::Error::custom)?,
-                );
-            } else {
-                mods.push(ModifiersKey::from_keyname(token).map_err(serde::de::Error::custom)?);
+/// What a hotkey does while held.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Behavior {
+    /// Acts once on every press.
+    #[default]
+    Press,
+    /// Acts until the keys are released, only for toggle, freeze and profile
+    /// hotkeys.
+    Hold,
+}
+
+/// A hotkey, written as its keys like `"ctrl+alt+c"`, or as a table like
+/// `{ keys = "ctrl+alt+c", behavior = "hold" }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub keys: KeySequence,
+    pub behavior: Behavior,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingOptions {
+    keys: KeySequence,
+    #[serde(default)]
+    behavior: Behavior,
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct BindingVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BindingVisitor {
+            type Value = Binding;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("keys like \"ctrl+alt+c\", or a table of keys and behavior")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Binding, E> {
+                Ok(Binding {
+                    keys: KeySequence::parse(s).map_err(E::custom)?,
+                    behavior: Behavior::Press,
+                })
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Binding, A::Error> {
+                let options =
+                    BindingOptions::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                Ok(Binding {
+                    keys: options.keys,
+                    behavior: options.behavior,
+                })
             }
         }
 
-        Ok(KeySequence {
-            vk: vk.ok_or_else(|| serde::de::Error::custom("No virtual key found"))?,
-            mods,
-        })
+        deserializer.deserialize_any(BindingVisitor)
     }
 }
 
-#[derive(Deserialize)]
+impl Serialize for Binding {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        if self.behavior == Behavior::Press {
+            return self.keys.serialize(serializer);
+        }
+        let mut table = serializer.serialize_struct("Binding", 2)?;
+        table.serialize_field("keys", &self.keys)?;
+        table.serialize_field("behavior", &self.behavior)?;
+        table.end()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct Config {
-    pub toggle: KeySequence,
-    pub freeze: KeySequence,
-    pub mode: FilterMode,
+    pub toggle: Binding,
+    pub freeze: Binding,
+    /// Rotate the range of [`FilterMode::Hue`] passes.
+    pub hue_next: Option<Binding>,
+    pub hue_prev: Option<Binding>,
+    /// Widen or narrow the range of [`FilterMode::Hue`] passes.
+    pub hue_wider: Option<Binding>,
+    pub hue_narrower: Option<Binding>,
+    /// Picks the color under the cursor, every second pick reports the
+    /// contrast between the last two.
+    pub contrast_pick: Option<Binding>,
+    /// Cycle through the profiles, or every mode without profiles.
+    pub next_mode: Option<Binding>,
+    pub prev_mode: Option<Binding>,
+    /// Switches back to the configured filter and undoes hue adjustments.
+    pub reset: Option<Binding>,
+    pub mode: Option<FilterMode>,
+    /// Filters applied one after another, instead of `mode`.
+    #[serde(default)]
+    pub pipeline: Vec<FilterMode>,
+    #[serde(default)]
+    pub transform: Transform,
     pub launch_on_startup: bool,
+    #[serde(deserialize_with = "refresh_rate")]
     pub refresh_rate: u32,
+    /// Filters switched to with their own hotkeys or cycled through, in name
+    /// order.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named filter, switched to with `key` while running. Pressing the key of
+/// the active profile switches back to the filter of the config. Unset
+/// options are taken from the config.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub key: Option<Binding>,
+    pub mode: Option<FilterMode>,
+    #[serde(default)]
+    pub pipeline: Vec<FilterMode>,
+    pub transform: Option<Transform>,
+}
+
+/// What the render loop shows.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    /// The profile, `None` for the filter of the config itself and presets.
+    pub name: Option<String>,
+    pub passes: Vec<FilterMode>,
+    pub transform: Transform,
+}
+
+impl Filter {
+    /// Shown when switching to the filter, e.g. `value_check: OklabGrayscale + Posterize`.
+    pub fn label(&self) -> String {
+        let modes = self
+            .passes
+            .iter()
+            .map(FilterMode::name)
+            .collect::<Vec<_>>()
+            .join(" + ");
+        match &self.name {
+            Some(name) => format!("{}: {}", name, modes),
+            None => modes,
+        }
+    }
+}
+
+/// Highest `refresh_rate`, 0 doesn't limit it.
+pub const MAX_REFRESH_RATE: u32 = 1000;
+
+fn refresh_rate<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let rate = u32::deserialize(deserializer)?;
+    if rate > MAX_REFRESH_RATE {
+        return Err(serde::de::Error::custom(format!(
+            "Refresh rate must be at most {}, or 0 to not limit it, got {}",
+            MAX_REFRESH_RATE, rate
+        )));
+    }
+    Ok(rate)
+}
+
+impl Config {
+    /// Every bound hotkey with its key in the config.
+    fn bindings(&self) -> Vec<(String, &Binding)> {
+        let profiles = self
+            .profiles
+            .iter()
+            .map(|(name, profile)| (format!("profiles.{}.key", name), profile.key.as_ref()));
+        [
+            ("toggle", Some(&self.toggle)),
+            ("freeze", Some(&self.freeze)),
+            ("hue_next", self.hue_next.as_ref()),
+            ("hue_prev", self.hue_prev.as_ref()),
+            ("hue_wider", self.hue_wider.as_ref()),
+            ("hue_narrower", self.hue_narrower.as_ref()),
+            ("contrast_pick", self.contrast_pick.as_ref()),
+            ("next_mode", self.next_mode.as_ref()),
+            ("prev_mode", self.prev_mode.as_ref()),
+            ("reset", self.reset.as_ref()),
+        ]
+        .map(|(name, key)| (name.to_string(), key))
+        .into_iter()
+        .chain(profiles)
+        .filter_map(|(name, key)| Some((name, key?)))
+        .collect()
+    }
+
+    /// The filter of the config, followed by the filter of each profile, or
+    /// every preset without profiles.
+    pub fn filters(&self) -> Vec<Filter> {
+        let base = Filter {
+            name: None,
+            passes: self.passes(),
+            transform: self.transform,
+        };
+        let profiles = self.profiles.iter().map(|(name, profile)| Filter {
+            name: Some(name.clone()),
+            passes: match &profile.mode {
+                Some(mode) => vec![mode.clone()],
+                None if profile.pipeline.is_empty() => base.passes.clone(),
+                None => profile.pipeline.clone(),
+            },
+            transform: profile.transform.unwrap_or(base.transform),
+        });
+        if self.profiles.is_empty() {
+            let presets = FilterMode::presets().into_iter().map(|mode| Filter {
+                name: None,
+                passes: vec![mode],
+                transform: base.transform,
+            });
+            return std::iter::once(base.clone()).chain(presets).collect();
+        }
+        std::iter::once(base.clone()).chain(profiles).collect()
+    }
+
+    /// Every configured pass with its key in the config, including the ones
+    /// of profiles.
+    pub fn modes_mut(&mut self) -> Vec<(String, &mut FilterMode)> {
+        let mut modes = Vec::new();
+        modes.extend(self.mode.iter_mut().map(|m| ("mode".to_string(), m)));
+        modes.extend(
+            self.pipeline
+                .iter_mut()
+                .map(|m| ("pipeline".to_string(), m)),
+        );
+        for (name, profile) in &mut self.profiles {
+            modes.extend(
+                profile
+                    .mode
+                    .iter_mut()
+                    .map(|m| (format!("profiles.{}.mode", name), m)),
+            );
+            modes.extend(
+                profile
+                    .pipeline
+                    .iter_mut()
+                    .map(|m| (format!("profiles.{}.pipeline", name), m)),
+            );
+        }
+        modes
+    }
+
+    /// The passes to render, each one filters the output of the previous one.
+    pub fn passes(&self) -> Vec<FilterMode> {
+        match &self.mode {
+            Some(mode) => vec![mode.clone()],
+            None => self.pipeline.clone(),
+        }
+    }
+}
+
+/// A problem with the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The top level key the problem is in.
+    pub key: Option<String>,
+    /// 1-based line and column.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            key: None,
+            position: None,
+            message: message.into(),
+        }
+    }
+
+    /// Points the error at the top level key around `offset`.
+    fn at(mut self, source: &str, offset: usize) -> Self {
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        self.position = Some((
+            source[..offset].matches('\n').count() + 1,
+            source[line_start..offset].chars().count() + 1,
+        ));
+        if let Ok(table) = toml::de::DeTable::parse(source) {
+            self.key = table
+                .get_ref()
+                .iter()
+                .find(|(key, value)| key.span().start <= offset && offset < value.span().end)
+                .map(|(key, _)| key.get_ref().to_string());
+        }
+        self
+    }
+
+    /// Points the error at `key`, a dotted path which might not be in the
+    /// file.
+    fn at_key(self, source: &str, key: &str) -> Self {
+        let error = match key_span(source, key) {
+            Some(span) => self.at(source, span.start),
+            None => self,
+        };
+        Self {
+            key: Some(key.into()),
+            ..error
+        }
+    }
+}
+
+/// Where the dotted `path` is in `source`.
+fn key_span(source: &str, path: &str) -> Option<Range<usize>> {
+    let root = toml::de::DeTable::parse(source).ok()?.into_inner();
+    let mut table = Some(&root);
+    let mut span = None;
+    for part in path.split('.') {
+        let (key, value) = table?.iter().find(|(k, _)| k.get_ref() == part)?;
+        span = Some(key.span());
+        table = value.get_ref().as_table();
+    }
+    span
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((line, column)) = self.position {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, "{}: ", key)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Formats the errors one per line.
+fn format_errors(errors: &[ConfigError]) -> String {
+    errors
+        .iter()
+        .map(ConfigError::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses and validates the config, reporting every problem found after it
+/// parsed.
+pub fn parse_config(config_str: &str) -> Result<Config, Vec<ConfigError>> {
+    let config = toml::from_str::<Config>(config_str).map_err(|err| {
+        let error = ConfigError::new(err.message());
+        vec![match err.span() {
+            Some(span) => error.at(config_str, span.start),
+            None => error,
+        }]
+    })?;
+
+    let mut errors = Vec::new();
+    if config.mode.is_some() && !config.pipeline.is_empty() {
+        errors.push(
+            ConfigError::new("Only one of mode or pipeline can be set")
+                .at_key(config_str, "pipeline"),
+        );
+    } else if config.mode.is_none() && config.pipeline.is_empty() {
+        let error = ConfigError::new("One of mode or pipeline has to be set");
+        // Only an empty pipeline can be pointed at.
+        errors.push(match key_span(config_str, "pipeline") {
+            Some(_) => error.at_key(config_str, "pipeline"),
+            None => error,
+        });
+    }
+    for (name, profile) in &config.profiles {
+        if profile.mode.is_some() && !profile.pipeline.is_empty() {
+            errors.push(
+                ConfigError::new("Only one of mode or pipeline can be set")
+                    .at_key(config_str, &format!("profiles.{}", name)),
+            );
+        }
+    }
+
+    let bindings = config.bindings();
+    for (i, (name, binding)) in bindings.iter().enumerate() {
+        let key = &binding.keys;
+        if let Some((first, _)) = bindings[..i].iter().find(|(_, b)| b.keys.same_keys(key)) {
+            errors.push(
+                ConfigError::new(format!("{} is already bound to {}", key, first))
+                    .at_key(config_str, name),
+            );
+        }
+        if key.blocks_typing() {
+            errors.push(
+                ConfigError::new(format!("{} would block typing, add ctrl, alt or win", key))
+                    .at_key(config_str, name),
+            );
+        }
+        let holdable =
+            matches!(name.as_str(), "toggle" | "freeze") || name.starts_with("profiles.");
+        if binding.behavior == Behavior::Hold && !holdable {
+            errors.push(
+                ConfigError::new("Only toggle, freeze and profile hotkeys can be held")
+                    .at_key(config_str, &format!("{}.behavior", name)),
+            );
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reads, validates and loads the files of the config at `path` like the
+/// overlay would.
+pub fn check_config(path: &Path) -> Result<Config, Vec<ConfigError>> {
+    let source = std::fs::read_to_string(path).map_err(|err| {
+        vec![ConfigError::new(format!(
+            "Unable to read {}: {}",
+            path.display(),
+            err
+        ))]
+    })?;
+    let mut config = parse_config(&source)?;
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let errors = config
+        .modes_mut()
+        .into_iter()
+        .filter_map(|(key, mode)| {
+            Some(ConfigError::new(mode.resolve(dir).err()?).at_key(&source, &key))
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
 }
 
 const CONFIG_FILE: &str = "config.toml";
 const DEFAULT_CONFIG: &str = include_str!("./default_config.toml");
 
-pub fn get_config() -> Receiver<Config> {
+/// Sends the config whenever it changes, or why it couldn't be loaded.
+pub fn get_config() -> Receiver<Result<Config, String>> {
     let (config_tx, config_rx) = std::sync::mpsc::channel();
 
     std::thread::spawn(move || {
@@ -88,24 +1597,624 @@ pub fn get_config() -> Receiver<Config> {
             return;
         };
 
+        // Custom shaders and LUTs are reloaded like the config.
+        let config_dir = config_path.parent().unwrap();
+        let mut watched_files: Vec<PathBuf> = Vec::new();
+
         loop {
-            while let Ok(_) = config_change_rx.recv() {
+            while config_change_rx.recv().is_ok() {
                 log::info!("Config changed, reloading.");
 
-                let Ok(config_str) = std::fs::read_to_string(&config_path) else {
-                    log::error!("Unable to read the config file.");
-                    continue;
+                let config_str = match std::fs::read_to_string(&config_path) {
+                    Ok(config_str) => config_str,
+                    Err(err) => {
+                        log::error!("Unable to read the config file: {}", err);
+                        let _ =
+                            config_tx.send(Err(format!("Unable to read the config file: {}", err)));
+                        continue;
+                    }
                 };
 
-                let Ok(config) = toml::from_str(&config_str) else {
-                    log::error!("Unable to parse the config file.");
-                    continue;
+                let mut config = match parse_config(&config_str) {
+                    Ok(config) => config,
+                    Err(errors) => {
+                        let errors = format_errors(&errors);
+                        log::error!("Invalid config file:\n{}", errors);
+                        let _ = config_tx.send(Err(errors));
+                        continue;
+                    }
                 };
 
-                let _ = config_tx.send(config);
+                let mut files = Vec::new();
+                let mut load_error = None;
+                for (_, mode) in config.modes_mut() {
+                    if let Err(err) = mode.resolve(config_dir) {
+                        load_error = Some(err);
+                    }
+                    files.extend(mode.file().map(Path::to_path_buf));
+                }
+                if files != watched_files {
+                    for old in watched_files.drain(..) {
+                        let _ = debouncer.watcher().unwatch(&old);
+                    }
+                    for file in files {
+                        if let Err(err) = debouncer
+                            .watcher()
+                            .watch(&file, RecursiveMode::NonRecursive)
+                        {
+                            log::error!("Unable to watch {}: {}", file.display(), err);
+                        }
+                        watched_files.push(file);
+                    }
+                }
+                // Still watched, so fixing the file retries.
+                if let Some(err) = load_error {
+                    log::error!("Unable to load the filter: {}", err);
+                    let _ = config_tx.send(Err(format!("Unable to load the filter: {}", err)));
+                    continue;
+                }
+
+                let _ = config_tx.send(Ok(config));
             }
         }
     });
 
     config_rx
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `src` as the value of `mode`.
+    fn mode(src: &str) -> Result<FilterMode, String> {
+        #[derive(Deserialize)]
+        struct Options {
+            mode: FilterMode,
+        }
+        toml::from_str::<Options>(&format!("mode = {}", src))
+            .map(|options| options.mode)
+            .map_err(|err| err.message().to_string())
+    }
+
+    fn posterize(options: &str) -> Result<Posterize, String> {
+        match mode(&format!("{{ Posterize = {} }}", options))? {
+            FilterMode::Posterize(posterize) => Ok(posterize),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn key_sequence() {
+        let parse = |s: &str| KeySequence::parse(s);
+        let seq = parse("ctrl+alt+F9").unwrap();
+        assert_eq!(seq.key, Key::Function(9));
+        assert_eq!(seq.mods, [Modifier::Ctrl, Modifier::Alt]);
+        assert_eq!(parse("SHIFT+pause").unwrap().key, Key::Pause);
+        assert_eq!(parse("win+numpad5").unwrap().key, Key::Numpad(5));
+        assert_eq!(parse("ctrl+[").unwrap().key, Key::LeftBracket);
+        assert_eq!(parse("ctrl+PgUp").unwrap().key, Key::PageUp);
+        assert_eq!(parse("ctrl + Esc").unwrap().key, Key::Escape);
+        assert_eq!(parse("alt+7").unwrap().key, Key::Char('7'));
+        assert_eq!(parse("ctrl+plus").unwrap().key, Key::Plus);
+
+        assert_eq!(parse("ctrl+F25").unwrap_err(), "Invalid key: F25");
+        assert_eq!(parse("ctrl+hyper+c").unwrap_err(), "Invalid key: hyper");
+        assert_eq!(parse("ctrl+c+v").unwrap_err(), "Second key v after c");
+        assert_eq!(parse("ctrl+").unwrap_err(), "Empty key in ctrl+");
+        assert_eq!(
+            parse("ctrl+alt").unwrap_err(),
+            "No virtual key found in ctrl+alt"
+        );
+
+        for s in [
+            "ctrl+alt+c",
+            "shift+Pause",
+            "win+Numpad5",
+            "ctrl+[",
+            "alt+\\",
+            "norepeat+F24",
+            "ctrl+NumpadAdd",
+            "ctrl+Plus",
+            "ctrl+=",
+        ] {
+            let seq = parse(s).unwrap();
+            assert_eq!(seq.to_string(), s);
+            let value = toml::Value::try_from(&seq).unwrap();
+            assert_eq!(KeySequence::deserialize(value).unwrap(), seq);
+        }
+        for (key, names) in KEY_NAMES {
+            assert_eq!(Key::from_name(&names[0].to_uppercase()), Some(*key));
+        }
+    }
+
+    #[test]
+    fn binding() {
+        let source = DEFAULT_CONFIG.replace(
+            "toggle = \"ctrl+alt+c\"",
+            "toggle = { keys = \"ctrl+alt+c\", behavior = \"hold\" }",
+        );
+        let config = parse_config(&source).unwrap();
+        assert_eq!(config.toggle.behavior, Behavior::Hold);
+        assert_eq!(config.freeze.behavior, Behavior::Press);
+        let value = toml::Value::try_from(&config.toggle).unwrap();
+        assert_eq!(Binding::deserialize(value).unwrap(), config.toggle);
+        assert_eq!(
+            toml::Value::try_from(&config.freeze).unwrap(),
+            toml::Value::from("ctrl+alt+f")
+        );
+
+        let source = format!(
+            "{}[hue_next]\nkeys = \"ctrl+alt+l\"\nbehavior = \"hold\"",
+            DEFAULT_CONFIG
+        );
+        let line = source.lines().count();
+        let errs: Vec<_> = parse_config(&source)
+            .unwrap_err()
+            .iter()
+            .map(ConfigError::to_string)
+            .collect();
+        assert_eq!(
+            errs,
+            [format!(
+                "line {}, column 1: hue_next.behavior: Only toggle, freeze and profile hotkeys can be held",
+                line
+            )]
+        );
+        assert!(
+            parse_config(&DEFAULT_CONFIG.replace(
+                "freeze = \"ctrl+alt+f\"",
+                "freeze = { keys = \"ctrl+alt+f\", repeat = true }"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn default_config() {
+        let config = parse_config(DEFAULT_CONFIG).unwrap();
+        assert!(matches!(
+            config.passes().as_slice(),
+            [FilterMode::OklabGrayscale]
+        ));
+    }
+
+    #[test]
+    fn config_errors() {
+        fn errors(source: &str) -> Vec<String> {
+            parse_config(source)
+                .unwrap_err()
+                .iter()
+                .map(ConfigError::to_string)
+                .collect()
+        }
+
+        let base = DEFAULT_CONFIG.replace("mode = \"OklabGrayscale\"", "");
+        let source = format!("{}mode = \"Sepia\"", base);
+        let errs = errors(&source);
+        assert_eq!(errs.len(), 1);
+        let line = source.lines().count();
+        assert!(
+            errs[0].starts_with(&format!(
+                "line {}, column 8: mode: unknown variant `Sepia`",
+                line
+            )),
+            "{}",
+            errs[0]
+        );
+
+        let errs = errors(&DEFAULT_CONFIG.replace("refresh_rate = 0", "refresh_rate = 5000"));
+        assert!(
+            errs[0].ends_with(
+                "refresh_rate: Refresh rate must be at most 1000, or 0 to not limit it, got 5000"
+            ),
+            "{}",
+            errs[0]
+        );
+
+        let source = format!(
+            "{}hue_next = \"alt+ctrl+c\"\nhue_prev = \"shift+x\"",
+            DEFAULT_CONFIG
+        );
+        let line = source.lines().count() - 2;
+        assert_eq!(
+            errors(&source),
+            [
+                format!(
+                    "line {}, column 1: hue_next: alt+ctrl+c is already bound to toggle",
+                    line + 1
+                ),
+                format!(
+                    "line {}, column 1: hue_prev: shift+x would block typing, add ctrl, alt or win",
+                    line + 2
+                ),
+            ]
+        );
+        assert!(parse_config(&DEFAULT_CONFIG.replace("ctrl+alt+f", "ctrl+alt+F9")).is_ok());
+
+        let source = DEFAULT_CONFIG
+            .replace("ctrl+alt+c", "ctrl+Plus")
+            .replace("ctrl+alt+f", "ctrl+=");
+        assert!(errors(&source)[0].ends_with("freeze: ctrl+= is already bound to toggle"));
+
+        let errs = errors("toggle = \"ctrl+alt+c\"\nfreeze = \"ctrl+hyper+f\"");
+        assert_eq!(errs, ["line 2, column 10: freeze: Invalid key: hyper"]);
+    }
+
+    #[test]
+    fn profiles() {
+        let config = parse_config(&format!(
+            "{}
+[profiles.value_check]
+pipeline = [\"OklabGrayscale\", {{ Posterize = {{ bands = 5 }} }}]
+key = \"ctrl+alt+1\"
+
+[profiles.colorblind]
+mode = {{ Deuteranopia = {{}} }}
+key = \"ctrl+alt+2\"
+
+[profiles.flip]
+transform = \"FlipHorizontal\"
+",
+            DEFAULT_CONFIG.replace("transform = \"None\"", "transform = \"FlipVertical\"")
+        ))
+        .unwrap();
+
+        let filters = config.filters();
+        let names: Vec<_> = filters.iter().map(|f| f.name.as_deref()).collect();
+        assert_eq!(
+            names,
+            [None, Some("colorblind"), Some("flip"), Some("value_check")]
+        );
+        assert!(matches!(
+            filters[1].passes.as_slice(),
+            [FilterMode::Deuteranopia { severity }] if *severity == 1.0
+        ));
+        assert_eq!(filters[1].transform, Transform::FlipVertical);
+        // Unset options come from the config.
+        assert!(matches!(
+            filters[2].passes.as_slice(),
+            [FilterMode::OklabGrayscale]
+        ));
+        assert_eq!(filters[2].transform, Transform::FlipHorizontal);
+        assert!(matches!(
+            filters[3].passes.as_slice(),
+            [FilterMode::OklabGrayscale, FilterMode::Posterize(_)]
+        ));
+        assert_eq!(config.profiles["flip"].key, None);
+
+        let source = format!(
+            "{}[profiles.a]\nkey = \"ctrl+alt+f\"\nmode = \"LabGrayscale\"\npipeline = [\"LabGrayscale\"]",
+            DEFAULT_CONFIG
+        );
+        let line = source.lines().count() - 3;
+        let errors: Vec<_> = parse_config(&source)
+            .unwrap_err()
+            .iter()
+            .map(ConfigError::to_string)
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                format!(
+                    "line {}, column 11: profiles.a: Only one of mode or pipeline can be set",
+                    line
+                ),
+                format!(
+                    "line {}, column 1: profiles.a.key: ctrl+alt+f is already bound to freeze",
+                    line + 1
+                ),
+            ]
+        );
+        assert!(parse_config(&format!("{}[profiles.a]\nhotkey = \"F1\"", DEFAULT_CONFIG)).is_err());
+    }
+
+    #[test]
+    fn presets() {
+        let presets = FilterMode::presets();
+        let names: Vec<_> = presets.iter().map(FilterMode::name).collect();
+        assert_eq!(names.len(), 14);
+        assert!(!names.contains(&"Custom") && !names.contains(&"Lut"));
+
+        let config = parse_config(&format!(
+            "{}next_mode = \"ctrl+alt+Right\"\nreset = \"ctrl+alt+Backspace\"",
+            DEFAULT_CONFIG
+        ))
+        .unwrap();
+        assert_eq!(config.next_mode.as_ref().unwrap().keys.key, Key::Right);
+        assert_eq!(config.reset.as_ref().unwrap().keys.key, Key::Backspace);
+        let filters = config.filters();
+        assert_eq!(filters.len(), 15);
+        assert_eq!(filters[0].label(), "OklabGrayscale");
+        assert_eq!(filters[14].label(), "Clipping");
+
+        let filter = Filter {
+            name: Some("value_check".into()),
+            passes: vec![FilterMode::OklabGrayscale, FilterMode::LabGrayscale],
+            transform: Transform::None,
+        };
+        assert_eq!(filter.label(), "value_check: OklabGrayscale + LabGrayscale");
+    }
+
+    #[test]
+    fn pipeline() {
+        let base = DEFAULT_CONFIG.replace("mode = \"OklabGrayscale\"", "");
+        let config = parse_config(&format!(
+            "{}\npipeline = [\"LabGrayscale\", {{ Posterize = {{ bands = 2 }} }}]",
+            base
+        ))
+        .unwrap();
+        assert!(matches!(
+            config.passes().as_slice(),
+            [FilterMode::LabGrayscale, FilterMode::Posterize(_)]
+        ));
+
+        assert_eq!(
+            parse_config(&base).unwrap_err(),
+            [ConfigError::new("One of mode or pipeline has to be set")]
+        );
+        let source = format!("{}\npipeline = []", base);
+        assert_eq!(
+            parse_config(&source).unwrap_err()[0].to_string(),
+            format!(
+                "line {}, column 1: pipeline: One of mode or pipeline has to be set",
+                source.lines().count()
+            )
+        );
+        let source = format!("{}\npipeline = [\"LabGrayscale\"]", DEFAULT_CONFIG);
+        assert_eq!(
+            parse_config(&source).unwrap_err()[0].to_string(),
+            format!(
+                "line {}, column 1: pipeline: Only one of mode or pipeline can be set",
+                source.lines().count()
+            )
+        );
+    }
+
+    #[test]
+    fn blur() {
+        let FilterMode::Blur(b) = mode("{ Blur = {} }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(b.radius, 8.0);
+        let FilterMode::Blur(b) = mode("{ Blur = { radius = 2.5 } }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(b.radius, 2.5);
+        assert_eq!(
+            mode("{ Blur = { radius = 0 } }").unwrap_err(),
+            "Blur radius must be above 0 and at most 64, got 0"
+        );
+        assert!(mode("{ Blur = { radius = 100 } }").is_err());
+    }
+
+    #[test]
+    fn edges() {
+        let FilterMode::Edges(e) = mode("{ Edges = {} }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(e.lightness, Lightness::Oklab);
+        assert_eq!((e.threshold, e.thickness), (0.1, 1));
+        assert!(!e.invert && !e.overlay);
+
+        let FilterMode::Edges(e) =
+            mode("{ Edges = { threshold = 0.3, thickness = 3, invert = true, overlay = true } }")
+                .unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!((e.threshold, e.thickness), (0.3, 3));
+        assert!(e.invert && e.overlay);
+
+        assert_eq!(
+            mode("{ Edges = { threshold = 0 } }").unwrap_err(),
+            "Edge threshold must be above 0 and at most 1, got 0"
+        );
+        assert_eq!(
+            mode("{ Edges = { thickness = 9 } }").unwrap_err(),
+            "Edge thickness must be between 1 and 8, got 9"
+        );
+    }
+
+    #[test]
+    fn hue() {
+        let FilterMode::Hue(h) = mode("{ Hue = { center = -30 } }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(h.center, 330.0);
+        assert_eq!(h.width, 60.0);
+        assert_eq!(h.falloff, 15.0);
+        assert!(mode("{ Hue = {} }").is_err());
+        assert_eq!(
+            mode("{ Hue = { center = 0, width = 0 } }").unwrap_err(),
+            "Hue width must be above 0 and at most 360, got 0"
+        );
+        assert_eq!(
+            mode("{ Hue = { center = 0, falloff = 200 } }").unwrap_err(),
+            "Hue falloff must be between 0 and 180, got 200"
+        );
+
+        let adjusted = h.adjusted(40.0, -100.0);
+        assert_eq!(adjusted.center, 10.0);
+        assert_eq!(adjusted.width, HUE_STEP);
+        assert_eq!(h.adjusted(0.0, 1000.0).width, 360.0);
+
+        let config =
+            parse_config(&format!("{}\nhue_next = \"ctrl+alt+l\"", DEFAULT_CONFIG)).unwrap();
+        assert_eq!(config.hue_next.unwrap().keys.key, Key::Char('L'));
+        assert_eq!(config.hue_prev, None);
+    }
+
+    #[test]
+    fn heatmap() {
+        let FilterMode::Heatmap(h) = mode("{ Heatmap = {} }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(h.lightness, Lightness::Oklab);
+        assert_eq!(h.colors.len(), VIRIDIS.len());
+        assert!(!h.legend);
+
+        let FilterMode::Heatmap(h) =
+            mode("{ Heatmap = { colormap = \"Turbo\", legend = true } }").unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(h.colors.len(), TURBO.len());
+        assert!(h.legend);
+
+        let FilterMode::Heatmap(h) =
+            mode("{ Heatmap = { colors = [\"#0000ff\", \"#ff0000\"], lightness = \"Lab\" } }")
+                .unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(h.colors, [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0]]);
+
+        assert_eq!(
+            mode("{ Heatmap = { colors = [\"#0000ff\"] } }").unwrap_err(),
+            "Expected 2 to 16 colors, got 1"
+        );
+        assert_eq!(
+            mode("{ Heatmap = { colormap = \"Turbo\", colors = [\"#0000ff\", \"#ff0000\"] } }")
+                .unwrap_err(),
+            "Set either colormap or colors, not both"
+        );
+    }
+
+    #[test]
+    fn chroma() {
+        let FilterMode::Chroma(c) = mode("{ Chroma = {} }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.model, Lightness::Oklab);
+        assert_eq!(c.colors, [[0.0; 3], [1.0; 3]]);
+        assert_eq!(c.clip, None);
+
+        let FilterMode::Chroma(c) = mode(
+            "{ Chroma = { model = \"Lab\", colormap = \"Turbo\", clip = 80, clip_color = \"#00ff00\" } }",
+        )
+        .unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.colors.len(), TURBO.len());
+        assert_eq!(c.clip, Some(80.0));
+        assert_eq!(c.clip_color, [0.0, 1.0, 0.0]);
+
+        assert_eq!(
+            mode("{ Chroma = { clip = -0.1 } }").unwrap_err(),
+            "Clip threshold must be finite and above 0"
+        );
+        assert_eq!(
+            mode("{ Chroma = { clip = nan } }").unwrap_err(),
+            "Clip threshold must be finite and above 0"
+        );
+        assert!(mode("{ Chroma = { clip = inf } }").is_err());
+        assert_eq!(
+            mode("{ Chroma = { clip_color = \"red\" } }").unwrap_err(),
+            "Invalid color: red"
+        );
+    }
+
+    #[test]
+    fn clipping() {
+        let FilterMode::Clipping(c) = mode("{ Clipping = {} }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!((c.shadows, c.highlights), (0, 255));
+        assert!(c.channels);
+        assert_eq!(c.style, ClippingStyle::Hatch);
+        assert_eq!(c.highlight_color, [1.0, 0.0, 0.0]);
+
+        let FilterMode::Clipping(c) = mode(
+            "{ Clipping = { shadows = 8, highlights = 247, style = \"Tint\", channel_color = \"#00ff00\" } }",
+        )
+        .unwrap() else {
+            unreachable!()
+        };
+        assert_eq!((c.shadows, c.highlights), (8, 247));
+        assert_eq!(c.style, ClippingStyle::Tint);
+        assert_eq!(c.channel_color, [0.0, 1.0, 0.0]);
+
+        assert_eq!(
+            mode("{ Clipping = { shadows = 200, highlights = 100 } }").unwrap_err(),
+            "Shadows must be below highlights, got 200 and 100"
+        );
+        assert!(mode("{ Clipping = { highlights = 300 } }").is_err());
+    }
+
+    #[test]
+    fn contrast() {
+        let FilterMode::Contrast(c) = mode("{ Contrast = {} }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.metric, ContrastMetric::Wcag);
+        assert_eq!(c.background, [0.0; 3]);
+        let FilterMode::Contrast(c) = mode("{ Contrast = { metric = \"Apca\" } }").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(c.background, [1.0; 3]);
+        let FilterMode::Contrast(c) =
+            mode("{ Contrast = { metric = \"Apca\", background = \"#000000\" } }").unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(c.background, [0.0; 3]);
+        assert_eq!(
+            mode("{ Contrast = { background = \"white\" } }").unwrap_err(),
+            "Invalid color: white"
+        );
+    }
+
+    #[test]
+    fn posterize_defaults() {
+        let p = posterize("{}").unwrap();
+        assert_eq!(p.lightness, Lightness::Oklab);
+        assert_eq!(p.breakpoints, [1.0 / 3.0, 2.0 / 3.0]);
+        assert_eq!(p.colors, [[0.0; 3], [0.5; 3], [1.0; 3]]);
+
+        let p = posterize("{ bands = 2, lightness = \"Lab\" }").unwrap();
+        assert_eq!(p.lightness, Lightness::Lab);
+        assert_eq!(p.breakpoints, [0.5]);
+        assert_eq!(p.colors, [[0.0; 3], [1.0; 3]]);
+    }
+
+    #[test]
+    fn posterize_explicit() {
+        let p = posterize(
+            "{ breakpoints = [0.2, 0.7], colors = [\"#000000\", \"#ff8000\", \"#FFFFFF\"] }",
+        )
+        .unwrap();
+        assert_eq!(p.breakpoints, [0.2, 0.7]);
+        assert_eq!(p.colors[1], [1.0, 128.0 / 255.0, 0.0]);
+        assert_eq!(p.colors[2], [1.0; 3]);
+    }
+
+    #[test]
+    fn posterize_errors() {
+        assert_eq!(
+            posterize("{ bands = 3, breakpoints = [0.5] }").unwrap_err(),
+            "Expected 2 breakpoints for 3 bands, got 1"
+        );
+        assert_eq!(
+            posterize("{ bands = 1 }").unwrap_err(),
+            "Band count must be between 2 and 16, got 1"
+        );
+        assert_eq!(
+            posterize("{ bands = 0, breakpoints = [0.5] }").unwrap_err(),
+            "Band count must be between 2 and 16, got 0"
+        );
+        assert_eq!(
+            posterize("{ breakpoints = [0.6, 0.4] }").unwrap_err(),
+            "Breakpoints must be ascending and between 0 and 1"
+        );
+        assert_eq!(
+            posterize("{ bands = 2, colors = [\"#000000\"] }").unwrap_err(),
+            "Expected 2 colors, got 1"
+        );
+        assert_eq!(
+            posterize("{ bands = 2, colors = [\"#000000\", \"white\"] }").unwrap_err(),
+            "Invalid color: white"
+        );
+        assert!(posterize("{ band = 2 }").is_err());
+    }
+}
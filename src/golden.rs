@@ -0,0 +1,370 @@
+//! Golden image regression tests of every [`FilterMode`].
+//!
+//! Each fixture is filtered with the CPU implementation in [`crate::color`] and
+//! compared against the expected images in `tests/golden`. Run the tests with
+//! `UPDATE_GOLDEN=1` to regenerate the expected images after an intended change.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::{Rgb, RgbImage, RgbaImage};
+
+use crate::config::{
+    Blur, Chroma, Clipping, ClippingStyle, Contrast, ContrastMetric, Edges, FilterMode, Heatmap,
+    Hue, Lightness, Posterize,
+};
+use crate::lut::{Cube, Lut};
+
+/// Maximum per-channel difference before a pixel counts as changed.
+const TOLERANCE: u8 = 1;
+
+fn modes() -> Vec<(&'static str, FilterMode)> {
+    vec![
+        ("oklab_grayscale", FilterMode::OklabGrayscale),
+        ("lab_grayscale", FilterMode::LabGrayscale),
+        (
+            "contrast_wcag",
+            FilterMode::Contrast(Contrast {
+                metric: ContrastMetric::Wcag,
+                background: [0.0; 3],
+            }),
+        ),
+        (
+            "contrast_apca",
+            FilterMode::Contrast(Contrast {
+                metric: ContrastMetric::Apca,
+                background: [1.0; 3],
+            }),
+        ),
+        ("protanopia", FilterMode::Protanopia { severity: 1.0 }),
+        ("deuteranopia", FilterMode::Deuteranopia { severity: 1.0 }),
+        ("tritanopia", FilterMode::Tritanopia { severity: 1.0 }),
+        ("achromatopsia", FilterMode::Achromatopsia { severity: 1.0 }),
+        ("protanopia_half", FilterMode::Protanopia { severity: 0.5 }),
+        (
+            "notan_3",
+            FilterMode::Posterize(Posterize {
+                lightness: Lightness::Oklab,
+                breakpoints: vec![1.0 / 3.0, 2.0 / 3.0],
+                colors: vec![[0.0; 3], [0.5; 3], [1.0; 3]],
+            }),
+        ),
+        (
+            "notan_lab_colors",
+            FilterMode::Posterize(Posterize {
+                lightness: Lightness::Lab,
+                breakpoints: vec![0.2, 0.5, 0.8],
+                colors: vec![
+                    [0.1, 0.1, 0.3],
+                    [0.4, 0.2, 0.2],
+                    [0.8, 0.6, 0.4],
+                    [1.0, 1.0, 0.9],
+                ],
+            }),
+        ),
+        ("blur_8", FilterMode::Blur(Blur { radius: 8.0 })),
+        (
+            "edges",
+            FilterMode::Edges(Edges {
+                lightness: Lightness::Oklab,
+                threshold: 0.1,
+                thickness: 1,
+                invert: false,
+                overlay: false,
+            }),
+        ),
+        (
+            "edges_thick_overlay",
+            FilterMode::Edges(Edges {
+                lightness: Lightness::Lab,
+                threshold: 0.05,
+                thickness: 3,
+                invert: true,
+                overlay: true,
+            }),
+        ),
+        (
+            "heatmap_legend",
+            FilterMode::Heatmap(Heatmap {
+                lightness: Lightness::Oklab,
+                colors: vec![[0.27, 0.0, 0.33], [0.13, 0.57, 0.55], [0.99, 0.91, 0.15]],
+                legend: true,
+            }),
+        ),
+        (
+            "heatmap_lab",
+            FilterMode::Heatmap(Heatmap {
+                lightness: Lightness::Lab,
+                colors: vec![[0.0, 0.0, 1.0], [1.0, 1.0, 1.0], [1.0, 0.0, 0.0]],
+                legend: false,
+            }),
+        ),
+        (
+            "chroma_oklch",
+            FilterMode::Chroma(Chroma {
+                model: Lightness::Oklab,
+                colors: vec![[0.0; 3], [1.0; 3]],
+                clip: None,
+                clip_color: [1.0, 0.0, 1.0],
+            }),
+        ),
+        (
+            "chroma_lab_clip",
+            FilterMode::Chroma(Chroma {
+                model: Lightness::Lab,
+                colors: vec![[0.27, 0.0, 0.33], [0.13, 0.57, 0.55], [0.99, 0.91, 0.15]],
+                clip: Some(80.0),
+                clip_color: [1.0, 0.0, 0.0],
+            }),
+        ),
+        (
+            "hue_greens",
+            FilterMode::Hue(Hue {
+                center: 140.0,
+                width: 60.0,
+                falloff: 20.0,
+            }),
+        ),
+        (
+            "clipping_hatch",
+            FilterMode::Clipping(Clipping {
+                shadows: 8,
+                highlights: 247,
+                channels: true,
+                style: ClippingStyle::Hatch,
+                shadow_color: [0.0, 0.5, 1.0],
+                highlight_color: [1.0, 0.0, 0.0],
+                channel_color: [1.0, 1.0, 0.0],
+            }),
+        ),
+        (
+            "clipping_tint",
+            FilterMode::Clipping(Clipping {
+                shadows: 0,
+                highlights: 255,
+                channels: false,
+                style: ClippingStyle::Tint,
+                shadow_color: [0.0, 0.5, 1.0],
+                highlight_color: [1.0, 0.0, 0.0],
+                channel_color: [1.0, 1.0, 0.0],
+            }),
+        ),
+        (
+            "lut_sepia",
+            FilterMode::Lut(Lut {
+                path: PathBuf::new(),
+                cube: Some(Arc::new(sepia_cube())),
+            }),
+        ),
+    ]
+}
+
+/// A coarse sepia tone with a contrast curve, so the interpolation between
+/// entries matters.
+fn sepia_cube() -> Cube {
+    const SIZE: usize = 9;
+    let table = (0..SIZE.pow(3))
+        .map(|i| {
+            let [r, g, b] =
+                [i % SIZE, i / SIZE % SIZE, i / SIZE / SIZE].map(|c| c as f32 / (SIZE - 1) as f32);
+            let y = r * 0.3 + g * 0.59 + b * 0.11;
+            let y = y * y * (3.0 - 2.0 * y);
+            [y * 1.07, y * 0.74 + 0.05, y * 0.43 + 0.08].map(|c| c.min(1.0))
+        })
+        .collect();
+    Cube {
+        size: SIZE,
+        domain_min: [0.0; 3],
+        domain_max: [1.0; 3],
+        table,
+    }
+}
+
+/// The shader and the CPU implementation of each mode.
+const SOURCES: &[(&str, &str)] = &[
+    (
+        include_str!("./shaders/oklab_grayscale.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/lab_grayscale.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/contrast.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/cvd.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/blur.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/edges.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (include_str!("./shaders/lut.hlsl"), include_str!("./lut.rs")),
+    (
+        include_str!("./shaders/heatmap.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/chroma.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/hue.hlsl"),
+        include_str!("./color.rs"),
+    ),
+    (
+        include_str!("./shaders/clipping.hlsl"),
+        include_str!("./color.rs"),
+    ),
+];
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Gray, red, green and blue ramps stacked on top of each other.
+fn gradients() -> RgbaImage {
+    RgbaImage::from_fn(256, 64, |x, y| {
+        let v = x as u8;
+        image::Rgba(match y / 16 {
+            0 => [v, v, v, 255],
+            1 => [v, 0, 0, 255],
+            2 => [0, v, 0, 255],
+            _ => [0, 0, v, 255],
+        })
+    })
+}
+
+/// Black, the sRGB primaries and secondaries, and white.
+fn primaries() -> RgbaImage {
+    const SWATCHES: [[u8; 3]; 8] = [
+        [0, 0, 0],
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [0, 255, 255],
+        [255, 0, 255],
+        [255, 255, 0],
+        [255, 255, 255],
+    ];
+    RgbaImage::from_fn(64, 8, |x, _| {
+        let [r, g, b] = SWATCHES[x as usize / 8];
+        image::Rgba([r, g, b, 255])
+    })
+}
+
+fn fixtures() -> Vec<(&'static str, RgbaImage)> {
+    let picker = Path::new(env!("CARGO_MANIFEST_DIR")).join("images/oklch_picker.png");
+    vec![
+        ("gradients", gradients()),
+        ("primaries", primaries()),
+        ("oklch_picker", image::open(picker).unwrap().into_rgba8()),
+    ]
+}
+
+fn filter(mode: &FilterMode, fixture: &RgbaImage) -> RgbImage {
+    let mut image = fixture.clone();
+    let (width, height) = image.dimensions();
+    mode.apply_cpu(&mut image, width, height);
+    image::DynamicImage::ImageRgba8(image).into_rgb8()
+}
+
+/// Returns the number of pixels off by more than [`TOLERANCE`] and the largest
+/// difference of any channel.
+fn compare(actual: &RgbImage, expected: &RgbImage) -> (usize, u8) {
+    let mut mismatches = 0;
+    let mut max_diff = 0;
+    for (Rgb(a), Rgb(e)) in actual.pixels().zip(expected.pixels()) {
+        let diff = (0..3).map(|c| a[c].abs_diff(e[c])).max().unwrap();
+        max_diff = max_diff.max(diff);
+        if diff > TOLERANCE {
+            mismatches += 1;
+        }
+    }
+    (mismatches, max_diff)
+}
+
+#[test]
+fn golden_images() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for (fixture_name, fixture) in fixtures() {
+        for (mode_name, mode) in modes() {
+            let actual = filter(&mode, &fixture);
+            let path = golden_dir()
+                .join(mode_name)
+                .join(format!("{fixture_name}.png"));
+
+            if update {
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                actual.save(&path).unwrap();
+                continue;
+            }
+
+            let Ok(expected) = image::open(&path) else {
+                failures.push(format!("{}: missing expected image", path.display()));
+                continue;
+            };
+            let expected = expected.into_rgb8();
+            if expected.dimensions() != actual.dimensions() {
+                failures.push(format!("{}: size differs", path.display()));
+                continue;
+            }
+
+            let (mismatches, max_diff) = compare(&actual, &expected);
+            if mismatches > 0 {
+                let actual_path = std::env::temp_dir()
+                    .join(format!("screen_filter_{mode_name}_{fixture_name}.png"));
+                actual.save(&actual_path).unwrap();
+                failures.push(format!(
+                    "{}: {} pixels differ by up to {}, actual output saved to {}",
+                    path.display(),
+                    mismatches,
+                    max_diff,
+                    actual_path.display()
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+fn numeric_literals(code: &str) -> Vec<f64> {
+    code.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+        .filter(|token| token.starts_with(|c: char| c.is_ascii_digit()))
+        .filter_map(|token| {
+            token
+                .trim_end_matches("f32")
+                .trim_end_matches('f')
+                .parse::<f64>()
+                .ok()
+        })
+        .collect()
+}
+
+/// The GPU can't be tested on CI, so every constant in a shader has to show up
+/// in its CPU implementation, otherwise the golden images don't cover it.
+#[test]
+fn shader_constants_match_cpu() {
+    for (shader, cpu) in SOURCES {
+        let cpu = numeric_literals(cpu);
+        // Skip the license header.
+        let code = &shader[shader.find("SOFTWARE.").unwrap_or(0)..];
+        for constant in numeric_literals(code) {
+            assert!(
+                cpu.contains(&constant),
+                "shader constant {} is missing from the CPU implementation",
+                constant
+            );
+        }
+    }
+}
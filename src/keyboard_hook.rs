@@ -0,0 +1,122 @@
+//! Low level keyboard hook for hotkeys with [`Behavior::Hold`], registered
+//! hotkeys only report presses.
+//!
+//! [`Behavior::Hold`]: crate::config::Behavior::Hold
+
+use std::cell::RefCell;
+
+use crossbeam_channel::Receiver;
+use windows::Win32::{
+    Foundation::{LPARAM, LRESULT, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::{
+            GetAsyncKeyState, VIRTUAL_KEY, VK_CONTROL, VK_LWIN, VK_MENU, VK_RWIN, VK_SHIFT,
+        },
+        WindowsAndMessaging::{
+            CallNextHookEx, GetMessageW, KBDLLHOOKSTRUCT, MSG, PM_NOREMOVE, PeekMessageW,
+            PostThreadMessageW, SetWindowsHookExW, UnhookWindowsHookEx, WH_KEYBOARD_LL, WM_KEYDOWN,
+            WM_KEYUP, WM_QUIT, WM_SYSKEYDOWN, WM_SYSKEYUP,
+        },
+    },
+};
+
+use crate::{config::Binding, render::Action};
+
+struct HeldBinding {
+    vk: u32,
+    mods: [bool; 4],
+    action: Action,
+    held: bool,
+}
+
+thread_local! {
+    // The hook procedure runs on the thread that installed it.
+    static BINDINGS: RefCell<Vec<HeldBinding>> = const { RefCell::new(Vec::new()) };
+}
+
+fn pressed(vk: VIRTUAL_KEY) -> bool {
+    unsafe { GetAsyncKeyState(vk.0 as i32) < 0 }
+}
+
+fn held_modifiers() -> [bool; 4] {
+    [
+        pressed(VK_MENU),
+        pressed(VK_CONTROL),
+        pressed(VK_SHIFT),
+        pressed(VK_LWIN) || pressed(VK_RWIN),
+    ]
+}
+
+unsafe extern "system" fn hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let event = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+        let down = matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN);
+        let up = matches!(wparam.0 as u32, WM_KEYUP | WM_SYSKEYUP);
+
+        let handled = BINDINGS.with_borrow_mut(|bindings| {
+            let mut handled = false;
+            for binding in bindings.iter_mut().filter(|b| b.vk == event.vkCode) {
+                if down && (binding.held || binding.mods == held_modifiers()) {
+                    // Auto-repeat keeps sending key downs.
+                    if !binding.held {
+                        binding.held = true;
+                        binding.action.hold();
+                    }
+                    handled = true;
+                } else if up && binding.held {
+                    binding.held = false;
+                    binding.action.release();
+                    handled = true;
+                }
+            }
+            handled
+        });
+        if handled {
+            return LRESULT(1);
+        }
+    }
+
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
+
+/// Runs the hook until the terminator fires, blocking the calling thread.
+pub fn hold_loop(
+    hotkeys: &[(Binding, Action)],
+    terminator: Receiver<()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    BINDINGS.set(
+        hotkeys
+            .iter()
+            .map(|(binding, action)| HeldBinding {
+                vk: binding.keys.key.vk_code() as u32,
+                mods: binding.keys.held_modifiers(),
+                action: *action,
+                held: false,
+            })
+            .collect(),
+    );
+
+    let hook = unsafe { SetWindowsHookExW(WH_KEYBOARD_LL, Some(hook_proc), None, 0)? };
+
+    // Makes sure the message queue exists before WM_QUIT is posted to it.
+    let mut msg = MSG::default();
+    let _ = unsafe { PeekMessageW(&mut msg, None, 0, 0, PM_NOREMOVE) };
+    let thread = unsafe { GetCurrentThreadId() };
+    std::thread::spawn(move || {
+        let _ = terminator.recv();
+        log::info!("Interrupting keyboard hook.");
+        let _ = unsafe { PostThreadMessageW(thread, WM_QUIT, WPARAM(0), LPARAM(0)) };
+    });
+
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {}
+
+    unsafe { UnhookWindowsHookEx(hook)? };
+    for binding in BINDINGS.take() {
+        if binding.held {
+            binding.action.release();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,237 @@
+//! Adobe / Resolve `.cube` 3D LUTs.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+/// Largest `LUT_3D_SIZE` accepted, common LUTs use 17, 33 or 65.
+pub const MAX_SIZE: usize = 256;
+
+/// A parsed 3D LUT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cube {
+    /// Entries along each axis.
+    pub size: usize,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    /// `size³` output colors, red changing fastest, then green, then blue.
+    pub table: Vec<[f32; 3]>,
+}
+
+impl Cube {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line_error = |message: &str| format!("Line {}: {}", i + 1, message);
+
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next().unwrap();
+            let triple = |tokens: std::str::SplitWhitespace| {
+                let values = tokens
+                    .map(|t| t.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| line_error("Invalid number"))?;
+                <[f32; 3]>::try_from(values).map_err(|_| line_error("Expected 3 values"))
+            };
+
+            match keyword {
+                "LUT_3D_SIZE" => {
+                    let value = tokens
+                        .next()
+                        .and_then(|t| t.parse::<usize>().ok())
+                        .ok_or_else(|| line_error("Invalid LUT_3D_SIZE"))?;
+                    if !(2..=MAX_SIZE).contains(&value) {
+                        return Err(line_error(&format!(
+                            "LUT_3D_SIZE must be between 2 and {}, got {}",
+                            MAX_SIZE, value
+                        )));
+                    }
+                    size = Some(value);
+                }
+                "LUT_1D_SIZE" => return Err("Only 3D LUTs are supported".into()),
+                "DOMAIN_MIN" => domain_min = triple(tokens)?,
+                "DOMAIN_MAX" => domain_max = triple(tokens)?,
+                // Resolve's variant of the domain.
+                "LUT_3D_INPUT_RANGE" => {
+                    let [min, max] = tokens
+                        .map(|t| t.parse::<f32>().ok())
+                        .collect::<Option<Vec<_>>>()
+                        .and_then(|v| <[f32; 2]>::try_from(v).ok())
+                        .ok_or_else(|| line_error("Invalid LUT_3D_INPUT_RANGE"))?;
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                }
+                // TITLE and vendor specific keywords.
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+                _ => table.push(triple(line.split_whitespace())?),
+            }
+        }
+
+        let size = size.ok_or("Missing LUT_3D_SIZE")?;
+        if table.len() != size.pow(3) {
+            return Err(format!(
+                "Expected {} entries, got {}",
+                size.pow(3),
+                table.len()
+            ));
+        }
+        if (0..3).any(|c| domain_min[c] >= domain_max[c]) {
+            return Err("DOMAIN_MIN must be below DOMAIN_MAX".into());
+        }
+
+        Ok(Self {
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+        Self::parse(&source).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Looks up `rgb` with trilinear interpolation, like the 3D texture in the
+    /// shader. Colors outside of the domain are clamped to it.
+    pub fn sample(&self, rgb: [f32; 3]) -> [f32; 3] {
+        // The shader samples at texel centers, `(x * (size - 1) + 0.5) / size`,
+        // so the domain bounds land exactly on the first and last entries.
+        let max_index = (self.size - 1) as f32;
+        let coords: [f32; 3] = std::array::from_fn(|c| {
+            let x = (rgb[c] - self.domain_min[c]) / (self.domain_max[c] - self.domain_min[c]);
+            x.clamp(0.0, 1.0) * max_index
+        });
+        let lower = coords.map(|x| (x.floor() as usize).min(self.size - 2));
+        let t: [f32; 3] = std::array::from_fn(|c| coords[c] - lower[c] as f32);
+
+        let entry = |r: usize, g: usize, b: usize| {
+            self.table
+                [lower[0] + r + (lower[1] + g) * self.size + (lower[2] + b) * self.size * self.size]
+        };
+        let lerp =
+            |a: [f32; 3], b: [f32; 3], t: f32| std::array::from_fn(|c| a[c] + (b[c] - a[c]) * t);
+
+        let g0 = lerp(
+            lerp(entry(0, 0, 0), entry(1, 0, 0), t[0]),
+            lerp(entry(0, 1, 0), entry(1, 1, 0), t[0]),
+            t[1],
+        );
+        let g1 = lerp(
+            lerp(entry(0, 0, 1), entry(1, 0, 1), t[0]),
+            lerp(entry(0, 1, 1), entry(1, 1, 1), t[0]),
+            t[1],
+        );
+        lerp(g0, g1, t[2])
+    }
+}
+
+/// A `.cube` file, relative to the config file. The table is read by
+/// [`FilterMode::resolve`](crate::config::FilterMode::resolve).
+#[derive(Deserialize, Clone)]
+#[serde(from = "PathBuf")]
+pub struct Lut {
+    pub path: PathBuf,
+    pub cube: Option<Arc<Cube>>,
+}
+
+impl From<PathBuf> for Lut {
+    fn from(path: PathBuf) -> Self {
+        Self { path, cube: None }
+    }
+}
+
+// The table is way too long for the log.
+impl std::fmt::Debug for Lut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Lut").field(&self.path).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2x2 LUT that inverts colors.
+    const INVERT: &str = "\
+TITLE \"Invert\"
+# Comment
+LUT_3D_SIZE 2
+
+1 1 1
+0 1 1
+1 0 1
+0 0 1
+1 1 0
+0 1 0
+1 0 0
+0 0 0
+";
+
+    #[test]
+    fn parse() {
+        let cube = Cube::parse(INVERT).unwrap();
+        assert_eq!(cube.size, 2);
+        assert_eq!(cube.domain_min, [0.0; 3]);
+        assert_eq!(cube.domain_max, [1.0; 3]);
+        assert_eq!(cube.table[1], [0.0, 1.0, 1.0]);
+        assert_eq!(cube.table[7], [0.0; 3]);
+
+        let cube = Cube::parse(&format!("LUT_3D_INPUT_RANGE 0 2\n{}", INVERT)).unwrap();
+        assert_eq!(cube.domain_max, [2.0; 3]);
+        let cube = Cube::parse(&format!("DOMAIN_MIN 0.1 0 0\n{}", INVERT)).unwrap();
+        assert_eq!(cube.domain_min, [0.1, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Cube::parse("0 0 0").unwrap_err(), "Missing LUT_3D_SIZE");
+        assert_eq!(
+            Cube::parse("LUT_1D_SIZE 2").unwrap_err(),
+            "Only 3D LUTs are supported"
+        );
+        assert_eq!(
+            Cube::parse("LUT_3D_SIZE 1").unwrap_err(),
+            "Line 1: LUT_3D_SIZE must be between 2 and 256, got 1"
+        );
+        assert_eq!(
+            Cube::parse("LUT_3D_SIZE 2\n0 0 0").unwrap_err(),
+            "Expected 8 entries, got 1"
+        );
+        assert_eq!(
+            Cube::parse("LUT_3D_SIZE 2\n0 0").unwrap_err(),
+            "Line 2: Expected 3 values"
+        );
+        assert_eq!(
+            Cube::parse("LUT_3D_SIZE 2\n0 x 0").unwrap_err(),
+            "Line 2: Invalid number"
+        );
+        assert_eq!(
+            Cube::parse(&format!("DOMAIN_MAX 0 1 1\n{}", INVERT)).unwrap_err(),
+            "DOMAIN_MIN must be below DOMAIN_MAX"
+        );
+    }
+
+    #[test]
+    fn sample() {
+        let cube = Cube::parse(INVERT).unwrap();
+        assert_eq!(cube.sample([0.0, 0.0, 0.0]), [1.0, 1.0, 1.0]);
+        assert_eq!(cube.sample([1.0, 0.0, 1.0]), [0.0, 1.0, 0.0]);
+        assert_eq!(cube.sample([0.25, 0.5, 0.75]), [0.75, 0.5, 0.25]);
+        // Clamped to the domain.
+        assert_eq!(cube.sample([-1.0, 2.0, 0.0]), [1.0, 0.0, 1.0]);
+
+        let cube = Cube::parse(&format!("LUT_3D_INPUT_RANGE 0 2\n{}", INVERT)).unwrap();
+        assert_eq!(cube.sample([1.0, 1.0, 1.0]), [0.5, 0.5, 0.5]);
+    }
+}
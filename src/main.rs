@@ -1,21 +1,38 @@
 #![windows_subsystem = "windows"]
+// There's no overlay for other platforms, only the platform independent parts are built.
+#![cfg_attr(not(any(windows, target_os = "linux")), allow(dead_code))]
 
-use std::{fs::File, io::Write, path::Path, sync::atomic::Ordering};
+use std::{fs::File, io::Write, path::Path};
 
 use auto_launch::AutoLaunch;
-use crossbeam_channel::Sender;
-use env_logger::{Builder, Target};
-use log::LevelFilter;
-use win_hotkey::{HotkeyManager, HotkeyManagerImpl};
-use winreg::{RegKey, enums::HKEY_CURRENT_USER};
-use winrt_notification::Toast;
-
-use crate::{
-    config::Config,
-    render::{ENABLED, FROZEN},
+
+use crate::config::Config;
+
+#[cfg(any(windows, target_os = "linux"))]
+use {
+    crate::config::{Binding, Filter},
+    crate::render::Action,
+    crossbeam_channel::{Receiver, Sender},
+    env_logger::{Builder, Target},
+    log::LevelFilter,
 };
 
+#[cfg(windows)]
+use {
+    crate::config::Behavior,
+    win_hotkey::{HotkeyManager, HotkeyManagerImpl},
+    winreg::{RegKey, enums::HKEY_CURRENT_USER},
+    winrt_notification::Toast,
+};
+
+mod cli;
+mod color;
 mod config;
+#[cfg(test)]
+mod golden;
+#[cfg(windows)]
+mod keyboard_hook;
+mod lut;
 mod render;
 mod update;
 
@@ -44,27 +61,70 @@ fn configure_auto_launch(config: &Config, path: &Path) {
     }
 }
 
+/// Every configured hotkey and what it does.
+#[cfg(any(windows, target_os = "linux"))]
+fn hotkeys(config: &Config) -> Vec<(Binding, Action)> {
+    let optional = [
+        (&config.hue_next, Action::HueNext),
+        (&config.hue_prev, Action::HuePrev),
+        (&config.hue_wider, Action::HueWider),
+        (&config.hue_narrower, Action::HueNarrower),
+        (&config.contrast_pick, Action::ContrastPick),
+        (&config.next_mode, Action::NextMode),
+        (&config.prev_mode, Action::PrevMode),
+        (&config.reset, Action::Reset),
+    ];
+    // The filter of the config itself comes first.
+    let profiles = config
+        .profiles
+        .values()
+        .enumerate()
+        .map(|(i, profile)| (&profile.key, Action::Profile(i + 1)));
+    [
+        (config.toggle.clone(), Action::Toggle),
+        (config.freeze.clone(), Action::Freeze),
+    ]
+    .into_iter()
+    .chain(
+        optional
+            .into_iter()
+            .chain(profiles)
+            .filter_map(|(key, action)| Some((key.clone()?, action))),
+    )
+    .collect()
+}
+
+/// Whether the hotkey loop started for `running` still fits `config`.
+#[cfg(any(windows, target_os = "linux"))]
+fn same_hotkeys(running: &Config, config: &Config) -> bool {
+    // Wayland has a signal for every profile, with a key or not.
+    #[cfg(target_os = "linux")]
+    if wayland_session() && running.profiles.len() != config.profiles.len() {
+        return false;
+    }
+    hotkeys(running) == hotkeys(config)
+}
+
+#[cfg(windows)]
 fn configure_hotkey(config: &Config) -> Result<HotkeyManager<()>, Box<dyn std::error::Error>> {
     let mut mgr = HotkeyManager::new();
     mgr.unregister_all()?;
-    mgr.register(
-        config.toggle.vk,
-        Some(&config.toggle.mods),
-        Some(|| {
-            ENABLED.fetch_xor(true, Ordering::Relaxed);
-        }),
-    )?;
-    mgr.register(
-        config.freeze.vk,
-        Some(&config.freeze.mods),
-        Some(|| {
-            FROZEN.fetch_xor(true, Ordering::Relaxed);
-        }),
-    )?;
+    for (binding, action) in hotkeys(config) {
+        if binding.behavior == Behavior::Hold {
+            continue;
+        }
+        let keys = binding.keys;
+        mgr.register(
+            keys.virtual_key(),
+            Some(&keys.modifiers()),
+            Some(move || action.run()),
+        )?;
+    }
 
     Ok(mgr)
 }
 
+#[cfg(windows)]
 fn register_app_id() {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let (classes, _) = hkcu
@@ -75,9 +135,58 @@ fn register_app_id() {
     appkey.set_value("DisplayName", &"Screen Filter").unwrap();
 }
 
+/// The binary uses the windows subsystem, so output of commands only shows up
+/// after attaching to the console it was started from.
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(windows)]
+fn show_notification(title: &str, text: Option<&str>) {
+    let mut toast = Toast::new(APP_ID).title(title);
+    if let Some(text) = text {
+        toast = toast.text1(text);
+    }
+    toast.show().unwrap();
+}
+
+/// Shows the notification with `notify-send`, which talks to
+/// `org.freedesktop.Notifications`. Without it there's only the log.
+#[cfg(target_os = "linux")]
+fn show_notification(title: &str, text: Option<&str>) {
+    match text {
+        Some(text) => log::info!("{} {}", title, text),
+        None => log::info!("{}", title),
+    }
+
+    let mut command = std::process::Command::new("notify-send");
+    command.arg("--app-name=Screen Filter").arg(title);
+    if let Some(text) = text {
+        command.arg(text);
+    }
+    match command.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => log::warn!("notify-send failed: {}", status),
+        Err(err) => log::warn!("Unable to run notify-send: {}", err),
+    }
+}
+
+#[cfg(any(windows, target_os = "linux"))]
 fn main() {
     std::panic::set_hook(Box::new(panic_handler));
 
+    if std::env::args().len() > 1 {
+        #[cfg(windows)]
+        attach_console();
+        std::process::exit(cli::run(std::env::args().skip(1)));
+    }
+
+    #[cfg(windows)]
     register_app_id();
 
     let path = std::env::current_exe().unwrap();
@@ -100,87 +209,147 @@ fn main() {
                 release.tag_name,
                 release.html_url
             );
-            Toast::new(APP_ID)
-                .title("Screen Filter Update Available")
-                .text1(&format!(
+            show_notification(
+                "Screen Filter Update Available",
+                Some(&format!(
                     "A new version of Screen Filter is available: {}, goto {} to download.",
                     release.tag_name, release.html_url
-                ))
-                .show()
-                .unwrap();
+                )),
+            );
         }
         Ok(None) => {
             log::info!("No updates available");
         }
         Err(err) => {
             log::error!("Failed to check for updates: {}", err);
-            Toast::new(APP_ID)
-                .title("Screen Filter Update Check Failed")
-                .text1(&format!("Failed to check for updates: {}", err))
-                .show()
-                .unwrap();
+            show_notification(
+                "Screen Filter Update Check Failed",
+                Some(&format!("Failed to check for updates: {}", err)),
+            );
         }
     }
 
     let config_receiver = config::get_config();
-    let mut last_terminator: Option<Sender<()>> = None;
+    let mut event_loop: Option<EventLoop> = None;
 
     while let Ok(config) = config_receiver.recv() {
-        if let Some(last_terminator) = &last_terminator {
+        let config = match config {
+            Ok(config) => config,
+            Err(err) => {
+                let title = if event_loop.is_some() {
+                    "Invalid config, keeping the last one."
+                } else {
+                    "Invalid config, Screen Filter not started."
+                };
+                show_notification(title, Some(&err));
+                continue;
+            }
+        };
+        configure_auto_launch(&config, &path);
+
+        if let Some(running) = &mut event_loop {
+            // Filters and profiles are picked up by the running render loop,
+            // only the rest needs a restart. A render loop that failed to
+            // start, e.g. on a broken custom shader, is gone and has to be
+            // restarted.
+            if same_hotkeys(&running.config, &config)
+                && running.config.refresh_rate == config.refresh_rate
+                && running.filters_tx.send(config.filters()).is_ok()
+            {
+                log::info!("Updating filter mode.");
+                running.config = config;
+                continue;
+            }
+
             log::info!("Terminating last event loop.");
-            let _ = last_terminator.send(());
-            Toast::new(APP_ID)
-                .title("Screen Filter restarted.")
-                .show()
-                .unwrap();
+            if let Some(running) = event_loop.take() {
+                running.stop();
+            }
+            show_notification("Screen Filter restarted.", None);
         } else {
             // First run
-            Toast::new(APP_ID)
-                .title("Screen Filter started.")
-                .show()
-                .unwrap();
+            show_notification("Screen Filter started.", None);
         }
 
-        let config = config;
-        configure_auto_launch(&config, &path);
-
         log::info!("Stating event loop.");
-        if let Some(terminator) = start_event_loop(config) {
-            last_terminator = Some(terminator.tx);
-        }
+        event_loop = start_event_loop(config);
     }
 }
 
-struct EventLoopTerminator {
-    tx: Sender<()>,
+#[cfg(not(any(windows, target_os = "linux")))]
+fn main() {
+    std::panic::set_hook(Box::new(panic_handler));
+
+    if std::env::args().len() > 1 {
+        std::process::exit(cli::run(std::env::args().skip(1)));
+    }
+
+    eprintln!(
+        "The Screen Filter overlay isn't supported on this platform, try the `apply` command instead."
+    );
 }
 
-fn start_event_loop(config: Config) -> Option<EventLoopTerminator> {
-    let fragment = config.mode.fragment_shader();
+#[cfg(any(windows, target_os = "linux"))]
+struct EventLoop {
+    terminator_tx: Sender<()>,
+    filters_tx: Sender<Vec<Filter>>,
+    config: Config,
+}
 
+#[cfg(any(windows, target_os = "linux"))]
+impl EventLoop {
+    /// Stops the render and hotkey threads by dropping the terminator, a
+    /// message would only reach one of them.
+    fn stop(self) {
+        drop(self.terminator_tx);
+    }
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+fn start_event_loop(config: Config) -> Option<EventLoop> {
     let (terminator_tx, terminator_rx) = crossbeam_channel::unbounded();
+    let (filters_tx, filters_rx) = crossbeam_channel::unbounded();
 
     let trx = terminator_rx.clone();
+    let filters = config.filters();
+    let passes = filters[0].passes.clone();
+    let transform = filters[0].transform;
+    let refresh_rate = if config.refresh_rate == 0 {
+        u32::MAX
+    } else {
+        config.refresh_rate
+    };
     std::thread::spawn(move || {
-        log::info!(
-            "Starting render loop: {:?} at {} fps",
-            config.mode,
-            config.refresh_rate
-        );
-        if let Err(err) = render::render_loop(
-            fragment,
-            if config.refresh_rate == 0 {
-                u32::MAX
-            } else {
-                config.refresh_rate
-            },
-            trx.clone(),
-        ) {
+        log::info!("Starting render loop: {:?} at {} fps", passes, refresh_rate);
+        #[cfg(windows)]
+        let result = render::DxgiBackend::new(passes, transform)
+            .map(|backend| render::render_loop(backend, filters, refresh_rate, trx, filters_rx));
+        #[cfg(target_os = "linux")]
+        let result = if wayland_session() {
+            render::WaylandBackend::new(passes, transform)
+                .map(|backend| render::render_loop(backend, filters, refresh_rate, trx, filters_rx))
+        } else {
+            render::X11Backend::new(passes, transform)
+                .map(|backend| render::render_loop(backend, filters, refresh_rate, trx, filters_rx))
+        };
+
+        if let Err(err) = result {
             log::error!("Render loop error: {}", err);
         }
     });
 
-    let mgr = match configure_hotkey(&config) {
+    start_hotkey_loop(&config, terminator_rx)?;
+
+    Some(EventLoop {
+        terminator_tx,
+        filters_tx,
+        config,
+    })
+}
+
+#[cfg(windows)]
+fn start_hotkey_loop(config: &Config, terminator_rx: Receiver<()>) -> Option<()> {
+    let mgr = match configure_hotkey(config) {
         Ok(ok) => ok,
         Err(err) => {
             log::error!("Hotkey manager error: {}", err);
@@ -188,12 +357,25 @@ fn start_event_loop(config: Config) -> Option<EventLoopTerminator> {
         }
     };
 
+    let held = hotkeys(config)
+        .into_iter()
+        .filter(|(binding, _)| binding.behavior == Behavior::Hold)
+        .collect::<Vec<_>>();
+    if !held.is_empty() {
+        let terminator_rx = terminator_rx.clone();
+        std::thread::spawn(move || {
+            log::info!("Starting keyboard hook.");
+            if let Err(err) = keyboard_hook::hold_loop(&held, terminator_rx) {
+                log::error!("Keyboard hook error: {}", err);
+            }
+        });
+    }
+
     let interrupt_handle = mgr.interrupt_handle();
     std::thread::spawn(move || {
-        if let Ok(_) = terminator_rx.recv() {
-            log::info!("Interrupting hotkey manager.");
-            interrupt_handle.interrupt();
-        }
+        let _ = terminator_rx.recv();
+        log::info!("Interrupting hotkey manager.");
+        interrupt_handle.interrupt();
     });
 
     std::thread::spawn(move || {
@@ -201,5 +383,38 @@ fn start_event_loop(config: Config) -> Option<EventLoopTerminator> {
         mgr.event_loop();
     });
 
-    Some(EventLoopTerminator { tx: terminator_tx })
+    Some(())
+}
+
+/// XWayland can't capture or grab keys outside of its own windows, so the
+/// Wayland backend is preferred whenever a compositor is around.
+#[cfg(target_os = "linux")]
+fn wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+#[cfg(target_os = "linux")]
+fn start_hotkey_loop(config: &Config, terminator_rx: Receiver<()>) -> Option<()> {
+    if wayland_session() {
+        log::info!(
+            "Hotkeys aren't available on Wayland, send SIGUSR1 to toggle and SIGUSR2 to freeze the filter, SIGRTMIN+1 to +4 adjust the hue range, SIGRTMIN+5 to +7 cycle and reset the mode and SIGRTMIN+8 onwards switch profiles."
+        );
+        let profiles = config.profiles.len();
+        std::thread::spawn(move || {
+            if let Err(err) = render::wayland::hotkey_loop(profiles, terminator_rx) {
+                log::error!("Hotkey error: {}", err);
+            }
+        });
+        return Some(());
+    }
+
+    let hotkeys = hotkeys(config);
+    std::thread::spawn(move || {
+        log::info!("Starting hotkey event loop.");
+        if let Err(err) = render::x11::hotkey_loop(&hotkeys, terminator_rx) {
+            log::error!("Hotkey error: {}", err);
+        }
+    });
+
+    Some(())
 }
@@ -0,0 +1,1413 @@
+#![allow(non_snake_case)]
+
+use std::ffi::{CString, c_void};
+use std::mem::{size_of, zeroed};
+use std::slice;
+use std::sync::Arc;
+
+use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Direct3D::Fxc::*;
+use windows::Win32::Graphics::Direct3D::*;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Dxgi::Common::*;
+use windows::Win32::Graphics::Dxgi::*;
+use windows::Win32::Graphics::Gdi::{DEFAULT_GUI_FONT, GetStockObject, UpdateWindow};
+use windows::Win32::System::LibraryLoader::*;
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::*;
+
+use crate::APP_ID;
+use crate::config::{
+    ClippingStyle, ContrastMetric, FilterMode, Lightness, MAX_BANDS, MAX_STOPS, Transform,
+};
+use crate::lut::Cube;
+use crate::render::{CaptureError, CaptureSource, INDICATOR_SIZE, OutputInfo, Presenter, Rect};
+
+#[repr(C)]
+struct SimpleVertex {
+    x: f32,
+    y: f32,
+    u: f32,
+    v: f32,
+}
+
+struct OutputDup {
+    dup: IDXGIOutputDuplication,
+    desktop_rect: RECT,
+    width: u32,
+    height: u32,
+    dest_tex: ID3D11Texture2D,
+    dest_srv: ID3D11ShaderResourceView,
+    /// Targets passes but the last render to, used in turns.
+    scratch: Vec<RenderTarget>,
+}
+
+/// An intermediate texture the size of an output.
+struct RenderTarget {
+    rtv: ID3D11RenderTargetView,
+    srv: ID3D11ShaderResourceView,
+}
+
+struct Globals {
+    device: ID3D11Device,
+    ctx: ID3D11DeviceContext,
+    swap_chain: IDXGISwapChain,
+    rtv: ID3D11RenderTargetView,
+    vs: ID3D11VertexShader,
+    input_layout: ID3D11InputLayout,
+    /// Quad of the passes reading an earlier pass.
+    vb: ID3D11Buffer,
+    /// Quad of the first pass, mirrored by the [`Transform`].
+    output_vb: ID3D11Buffer,
+    sampler: ID3D11SamplerState,
+    outputs: Vec<OutputDup>,
+}
+
+struct FragmentShader {
+    ps: ID3D11PixelShader,
+    /// Source the shader was compiled from.
+    source: String,
+    /// The shader is drawn once with each buffer, or once without any.
+    params: Vec<ID3D11Buffer>,
+    /// 3D texture of [`FilterMode::Lut`], bound after the desktop, with the
+    /// cube it was uploaded from.
+    lut: Option<(Arc<Cube>, ID3D11ShaderResourceView)>,
+}
+
+/// `Params` of `cvd.hlsl`.
+#[repr(C)]
+struct CvdParams {
+    deficiency: u32,
+    severity: f32,
+    _pad: [u32; 2],
+}
+
+/// `Params` of `contrast.hlsl`.
+#[repr(C)]
+struct ContrastParams {
+    apca: u32,
+    background: [f32; 3],
+}
+
+/// `Params` of `posterize.hlsl`, every array element takes a whole register.
+#[repr(C)]
+struct PosterizeParams {
+    lightness: u32,
+    bands: u32,
+    _pad: [u32; 2],
+    breakpoints: [[f32; 4]; MAX_BANDS],
+    colors: [[f32; 4]; MAX_BANDS],
+}
+
+/// `Params` of `lut.hlsl`.
+#[repr(C)]
+struct LutParams {
+    domain_min: [f32; 3],
+    size: f32,
+    domain_max: [f32; 3],
+    _pad: f32,
+}
+
+/// `Params` of `heatmap.hlsl`.
+#[repr(C)]
+struct HeatmapParams {
+    lightness: u32,
+    stops: u32,
+    legend: u32,
+    _pad: u32,
+    colors: [[f32; 4]; MAX_STOPS],
+}
+
+/// `Params` of `chroma.hlsl`.
+#[repr(C)]
+struct ChromaParams {
+    model: u32,
+    stops: u32,
+    clip: u32,
+    threshold: f32,
+    clip_color: [f32; 4],
+    colors: [[f32; 4]; MAX_STOPS],
+}
+
+/// `Params` of `hue.hlsl`.
+#[repr(C)]
+struct HueParams {
+    center: f32,
+    width: f32,
+    falloff: f32,
+    _pad: f32,
+}
+
+/// `Params` of `clipping.hlsl`.
+#[repr(C)]
+struct ClippingParams {
+    shadows: f32,
+    highlights: f32,
+    channels: u32,
+    hatch: u32,
+    shadow_color: [f32; 4],
+    highlight_color: [f32; 4],
+    channel_color: [f32; 4],
+}
+
+/// `Params` of `edges.hlsl`.
+#[repr(C)]
+struct EdgesParams {
+    lightness: u32,
+    threshold: f32,
+    thickness: f32,
+    invert: u32,
+    overlay: u32,
+    _pad: [u32; 3],
+}
+
+/// `Params` of `blur.hlsl`.
+#[repr(C)]
+struct BlurParams {
+    direction: [f32; 2],
+    sigma: f32,
+    radius: i32,
+}
+
+/// Packs the options of `mode` the way its shader expects them, one constant
+/// buffer per draw and none for shaders without options.
+fn shader_params(mode: &FilterMode) -> Vec<Vec<u8>> {
+    fn bytes<T>(params: &T) -> Vec<u8> {
+        unsafe { slice::from_raw_parts(params as *const T as *const u8, size_of::<T>()).to_vec() }
+    }
+    let cvd = |deficiency, severity| {
+        bytes(&CvdParams {
+            deficiency,
+            severity,
+            _pad: [0; 2],
+        })
+    };
+
+    vec![match mode {
+        FilterMode::OklabGrayscale | FilterMode::LabGrayscale | FilterMode::Custom(_) => {
+            return Vec::new();
+        }
+        FilterMode::Contrast(contrast) => bytes(&ContrastParams {
+            apca: (contrast.metric == ContrastMetric::Apca) as u32,
+            background: contrast.background,
+        }),
+        FilterMode::Protanopia { severity } => cvd(0, *severity),
+        FilterMode::Deuteranopia { severity } => cvd(1, *severity),
+        FilterMode::Tritanopia { severity } => cvd(2, *severity),
+        FilterMode::Achromatopsia { severity } => cvd(3, *severity),
+        FilterMode::Posterize(posterize) => {
+            let mut params = PosterizeParams {
+                lightness: match posterize.lightness {
+                    Lightness::Oklab => 0,
+                    Lightness::Lab => 1,
+                },
+                bands: posterize.colors.len() as u32,
+                _pad: [0; 2],
+                breakpoints: [[0.0; 4]; MAX_BANDS],
+                colors: [[0.0; 4]; MAX_BANDS],
+            };
+            for (dst, breakpoint) in params.breakpoints.iter_mut().zip(&posterize.breakpoints) {
+                dst[0] = *breakpoint;
+            }
+            for (dst, [r, g, b]) in params.colors.iter_mut().zip(&posterize.colors) {
+                *dst = [*r, *g, *b, 1.0];
+            }
+            bytes(&params)
+        }
+        FilterMode::Lut(lut) => {
+            let Some(cube) = &lut.cube else {
+                return Vec::new();
+            };
+            bytes(&LutParams {
+                domain_min: cube.domain_min,
+                size: cube.size as f32,
+                domain_max: cube.domain_max,
+                _pad: 0.0,
+            })
+        }
+        FilterMode::Heatmap(heatmap) => {
+            let mut params = HeatmapParams {
+                lightness: match heatmap.lightness {
+                    Lightness::Oklab => 0,
+                    Lightness::Lab => 1,
+                },
+                stops: heatmap.colors.len() as u32,
+                legend: heatmap.legend as u32,
+                _pad: 0,
+                colors: [[0.0; 4]; MAX_STOPS],
+            };
+            for (dst, [r, g, b]) in params.colors.iter_mut().zip(&heatmap.colors) {
+                *dst = [*r, *g, *b, 1.0];
+            }
+            bytes(&params)
+        }
+        FilterMode::Chroma(chroma) => {
+            let [r, g, b] = chroma.clip_color;
+            let mut params = ChromaParams {
+                model: match chroma.model {
+                    Lightness::Oklab => 0,
+                    Lightness::Lab => 1,
+                },
+                stops: chroma.colors.len() as u32,
+                clip: chroma.clip.is_some() as u32,
+                threshold: chroma.clip.unwrap_or(0.0),
+                clip_color: [r, g, b, 1.0],
+                colors: [[0.0; 4]; MAX_STOPS],
+            };
+            for (dst, [r, g, b]) in params.colors.iter_mut().zip(&chroma.colors) {
+                *dst = [*r, *g, *b, 1.0];
+            }
+            bytes(&params)
+        }
+        FilterMode::Hue(hue) => bytes(&HueParams {
+            center: hue.center,
+            width: hue.width,
+            falloff: hue.falloff,
+            _pad: 0.0,
+        }),
+        FilterMode::Clipping(clipping) => {
+            let rgba = |[r, g, b]: [f32; 3]| [r, g, b, 1.0];
+            bytes(&ClippingParams {
+                shadows: clipping.shadows as f32,
+                highlights: clipping.highlights as f32,
+                channels: clipping.channels as u32,
+                hatch: (clipping.style == ClippingStyle::Hatch) as u32,
+                shadow_color: rgba(clipping.shadow_color),
+                highlight_color: rgba(clipping.highlight_color),
+                channel_color: rgba(clipping.channel_color),
+            })
+        }
+        FilterMode::Edges(edges) => bytes(&EdgesParams {
+            lightness: match edges.lightness {
+                Lightness::Oklab => 0,
+                Lightness::Lab => 1,
+            },
+            threshold: edges.threshold,
+            thickness: edges.thickness as f32,
+            invert: edges.invert as u32,
+            overlay: edges.overlay as u32,
+            _pad: [0; 3],
+        }),
+        // Horizontal, then vertical.
+        FilterMode::Blur(blur) => {
+            return [[1.0, 0.0], [0.0, 1.0]]
+                .map(|direction| {
+                    bytes(&BlurParams {
+                        direction,
+                        sigma: blur.sigma(),
+                        radius: blur.taps(),
+                    })
+                })
+                .to_vec();
+        }
+    }]
+}
+
+/// Every draw of the pipeline, with the constant buffer to draw with.
+fn draws(frags: &[FragmentShader]) -> Vec<(&FragmentShader, Option<&ID3D11Buffer>)> {
+    frags
+        .iter()
+        .flat_map(|frag| {
+            if frag.params.is_empty() {
+                vec![(frag, None)]
+            } else {
+                frag.params
+                    .iter()
+                    .map(|params| (frag, Some(params)))
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+unsafe extern "system" fn WndProc(hWnd: HWND, msg: u32, wParam: WPARAM, lParam: LPARAM) -> LRESULT {
+    match msg {
+        WM_DESTROY => {
+            unsafe { PostQuitMessage(0) };
+            LRESULT(0)
+        }
+        _ => unsafe { DefWindowProcA(hWnd, msg, wParam, lParam) },
+    }
+}
+
+const VS_SRC: &str = r#"
+void main(in float2 pos : POSITION, in float2 tex : TEXCOORD,
+          out float4 outPos : SV_Position, out float2 outTex : TEXCOORD)
+{
+    outPos = float4(pos, 0.0f, 1.0f);
+    outTex = tex;
+}
+"#;
+
+/// Captures the desktop with DXGI desktop duplication and presents through
+/// D3D11 onto a click-through layered window.
+pub struct DxgiBackend {
+    hWnd: HWND,
+    g: Globals,
+    frags: Vec<FragmentShader>,
+    /// Created on first use.
+    indicator: Option<HWND>,
+}
+
+impl DxgiBackend {
+    pub fn new(passes: Vec<FilterMode>, transform: Transform) -> windows::core::Result<Self> {
+        unsafe {
+            let hinstance = GetModuleHandleA(None)?;
+            let class_name = s!("DX11ScreenFilter");
+
+            let wc = WNDCLASSEXA {
+                cbSize: size_of::<WNDCLASSEXA>() as u32,
+                style: CS_HREDRAW | CS_VREDRAW,
+                lpfnWndProc: Some(WndProc),
+                hInstance: hinstance.into(),
+                lpszClassName: class_name,
+                ..Default::default()
+            };
+            RegisterClassExA(&wc);
+
+            let virt_left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+            let virt_top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+            let virt_w = GetSystemMetrics(SM_CXVIRTUALSCREEN);
+            let virt_h = GetSystemMetrics(SM_CYVIRTUALSCREEN);
+
+            let hWnd = CreateWindowExA(
+                WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW,
+                class_name,
+                PCSTR::from_raw(APP_ID.as_ptr()),
+                WS_POPUP,
+                virt_left,
+                virt_top,
+                virt_w,
+                virt_h,
+                None,
+                None,
+                Some(hinstance.into()),
+                None,
+            )?;
+
+            SetLayeredWindowAttributes(hWnd, COLORREF(0), 255, LWA_ALPHA)?;
+
+            let mut g = init_d3d11(hWnd, transform)?;
+            init_duplications(&mut g)?;
+            let frags = passes
+                .iter()
+                .map(|mode| compile_shader(&g, mode))
+                .collect::<windows::core::Result<Vec<_>>>()?;
+            create_scratch(&mut g, draws(&frags).len())?;
+
+            Ok(Self {
+                hWnd,
+                g,
+                frags,
+                indicator: None,
+            })
+        }
+    }
+
+    /// Shows `text` at the top of the primary monitor.
+    unsafe fn show_indicator(&mut self, text: &str) -> windows::core::Result<()> {
+        unsafe {
+            let hWnd = match self.indicator {
+                Some(hWnd) => hWnd,
+                None => *self.indicator.insert(create_indicator()?),
+            };
+            let (width, height) = (INDICATOR_SIZE.0 as i32, INDICATOR_SIZE.1 as i32);
+            SetWindowTextW(hWnd, &HSTRING::from(text))?;
+            SetWindowPos(
+                hWnd,
+                Some(HWND_TOPMOST),
+                (GetSystemMetrics(SM_CXSCREEN) - width) / 2,
+                height,
+                width,
+                height,
+                SWP_NOACTIVATE | SWP_SHOWWINDOW,
+            )?;
+            // There's no message loop, so it's painted right away.
+            let _ = UpdateWindow(hWnd);
+            Ok(())
+        }
+    }
+}
+
+impl Drop for DxgiBackend {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DestroyWindow(self.hWnd);
+            if let Some(indicator) = self.indicator {
+                let _ = DestroyWindow(indicator);
+            }
+        }
+    }
+}
+
+/// A click-through popup of the system `STATIC` control, which draws its
+/// text by itself.
+unsafe fn create_indicator() -> windows::core::Result<HWND> {
+    unsafe {
+        // SS_CENTER | SS_CENTERIMAGE, centering the text of static controls.
+        let centered = WINDOW_STYLE(0x1 | 0x200);
+        let hWnd = CreateWindowExW(
+            WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            w!("STATIC"),
+            w!(""),
+            WS_POPUP | centered,
+            0,
+            0,
+            INDICATOR_SIZE.0 as i32,
+            INDICATOR_SIZE.1 as i32,
+            None,
+            None,
+            Some(GetModuleHandleA(None)?.into()),
+            None,
+        )?;
+        SetLayeredWindowAttributes(hWnd, COLORREF(0), 220, LWA_ALPHA)?;
+        SendMessageW(
+            hWnd,
+            WM_SETFONT,
+            Some(WPARAM(GetStockObject(DEFAULT_GUI_FONT).0 as usize)),
+            Some(LPARAM(1)),
+        );
+        // Left out of the captured desktop, like the overlay.
+        let _ = SetWindowDisplayAffinity(hWnd, WDA_EXCLUDEFROMCAPTURE);
+        Ok(hWnd)
+    }
+}
+
+impl Presenter for DxgiBackend {
+    fn set_visible(&mut self, visible: bool) {
+        unsafe {
+            let _ = ShowWindow(self.hWnd, if visible { SW_SHOW } else { SW_HIDE });
+        }
+    }
+
+    fn set_exclude_from_capture(&mut self, exclude: bool) {
+        unsafe {
+            let affinity = if exclude {
+                WDA_EXCLUDEFROMCAPTURE
+            } else {
+                WDA_NONE
+            };
+            let _ = SetWindowDisplayAffinity(self.hWnd, affinity);
+        }
+    }
+
+    fn set_pipeline(&mut self, passes: Vec<FilterMode>) {
+        unsafe {
+            // Options of the same shaders only need new constant buffers.
+            let same_shaders = passes.len() == self.frags.len()
+                && passes.iter().zip(&self.frags).all(|(mode, frag)| {
+                    shader_params(mode).len() == frag.params.len()
+                        && mode.fragment_shader().is_ok_and(|code| code == frag.source)
+                });
+            if same_shaders {
+                for (mode, frag) in passes.iter().zip(&mut self.frags) {
+                    for (buffer, params) in frag.params.iter().zip(shader_params(mode)) {
+                        self.g
+                            .ctx
+                            .UpdateSubresource(buffer, 0, None, params.as_ptr() as _, 0, 0);
+                    }
+                    let Some(cube) = lut_cube(mode) else {
+                        continue;
+                    };
+                    // Switching profiles keeps the loaded cube.
+                    if frag
+                        .lut
+                        .as_ref()
+                        .is_some_and(|(old, _)| Arc::ptr_eq(old, cube))
+                    {
+                        continue;
+                    }
+                    match create_lut(&self.g, cube) {
+                        Ok(lut) => frag.lut = Some((cube.clone(), lut)),
+                        Err(err) => log::error!("Unable to create LUT texture: {}", err),
+                    }
+                }
+                return;
+            }
+
+            // Errors are already reported, the previous shaders stay.
+            let Ok(frags) = passes
+                .iter()
+                .map(|mode| compile_shader(&self.g, mode))
+                .collect::<windows::core::Result<Vec<_>>>()
+            else {
+                return;
+            };
+            if let Err(err) = create_scratch(&mut self.g, draws(&frags).len()) {
+                log::error!("Unable to create intermediate render targets: {}", err);
+                return;
+            }
+            self.frags = frags;
+        }
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        unsafe {
+            match create_quad(&self.g.device, transform) {
+                Ok(vb) => self.g.output_vb = vb,
+                Err(err) => log::error!("Unable to create the output quad: {}", err),
+            }
+        }
+    }
+
+    fn set_indicator(&mut self, text: Option<&str>) {
+        unsafe {
+            match (text, self.indicator) {
+                (Some(text), _) => {
+                    if let Err(err) = self.show_indicator(text) {
+                        log::error!("Unable to show the indicator: {}", err);
+                    }
+                }
+                (None, Some(indicator)) => {
+                    let _ = ShowWindow(indicator, SW_HIDE);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn present(&mut self) {
+        unsafe { render(&self.g, &self.frags) }
+    }
+}
+
+impl CaptureSource for DxgiBackend {
+    fn outputs(&self) -> Vec<OutputInfo> {
+        self.g
+            .outputs
+            .iter()
+            .map(|od| OutputInfo {
+                desktop_rect: rect_from_win(od.desktop_rect),
+                width: od.width,
+                height: od.height,
+            })
+            .collect()
+    }
+
+    fn acquire_frame(&mut self, output: usize) -> std::result::Result<Vec<Rect>, CaptureError> {
+        unsafe { capture_desktop(&self.g, &self.g.outputs[output]) }
+    }
+
+    fn reinit(&mut self) -> std::result::Result<(), CaptureError> {
+        unsafe {
+            init_duplications(&mut self.g)
+                .and_then(|_| create_scratch(&mut self.g, draws(&self.frags).len()))
+                .map_err(|err| CaptureError::Other(err.into()))
+        }
+    }
+
+    fn cursor(&self) -> Option<(i32, i32)> {
+        let mut point = POINT::default();
+        unsafe { GetCursorPos(&mut point) }.ok()?;
+        Some((point.x, point.y))
+    }
+
+    fn pixel(&self, x: i32, y: i32) -> Option<[u8; 3]> {
+        let od = self
+            .g
+            .outputs
+            .iter()
+            .find(|od| rect_from_win(od.desktop_rect).contains(x, y))?;
+        let (x, y) = (x - od.desktop_rect.left, y - od.desktop_rect.top);
+        unsafe { read_pixel(&self.g, od, x as u32, y as u32) }.ok()
+    }
+}
+
+/// Reads one pixel of the last frame captured from `od` back from the GPU.
+unsafe fn read_pixel(
+    g: &Globals,
+    od: &OutputDup,
+    x: u32,
+    y: u32,
+) -> windows::core::Result<[u8; 3]> {
+    unsafe {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: 1,
+            Height: 1,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_STAGING,
+            CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+            ..Default::default()
+        };
+        let mut staging: Option<ID3D11Texture2D> = None;
+        g.device.CreateTexture2D(&desc, None, Some(&mut staging))?;
+        let staging = staging.unwrap();
+
+        let box_ = D3D11_BOX {
+            left: x,
+            top: y,
+            front: 0,
+            right: x + 1,
+            bottom: y + 1,
+            back: 1,
+        };
+        g.ctx
+            .CopySubresourceRegion(&staging, 0, 0, 0, 0, &od.dest_tex, 0, Some(&box_));
+
+        let mut mapped: D3D11_MAPPED_SUBRESOURCE = zeroed();
+        g.ctx
+            .Map(&staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+        let [b, green, r, _] = *(mapped.pData as *const [u8; 4]);
+        g.ctx.Unmap(&staging, 0);
+        Ok([r, green, b])
+    }
+}
+
+fn rect_from_win(rect: RECT) -> Rect {
+    Rect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    }
+}
+
+unsafe fn init_d3d11(hWnd: HWND, transform: Transform) -> windows::core::Result<Globals> {
+    unsafe {
+        let virt_w = GetSystemMetrics(SM_CXVIRTUALSCREEN) as u32;
+        let virt_h = GetSystemMetrics(SM_CYVIRTUALSCREEN) as u32;
+
+        let sd = DXGI_SWAP_CHAIN_DESC {
+            BufferCount: 1,
+            BufferDesc: DXGI_MODE_DESC {
+                Width: virt_w,
+                Height: virt_h,
+                RefreshRate: DXGI_RATIONAL {
+                    Numerator: 60,
+                    Denominator: 1,
+                },
+                Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+                ..Default::default()
+            },
+            BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            OutputWindow: hWnd,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Windowed: BOOL(1),
+            SwapEffect: DXGI_SWAP_EFFECT_DISCARD,
+            ..Default::default()
+        };
+
+        let mut device: Option<ID3D11Device> = None;
+        let mut ctx: Option<ID3D11DeviceContext> = None;
+        let mut swap_chain: Option<IDXGISwapChain> = None;
+
+        let feature_levels = [D3D_FEATURE_LEVEL_11_0];
+        let mut got_level = D3D_FEATURE_LEVEL_11_0;
+
+        D3D11CreateDeviceAndSwapChain(
+            None,
+            D3D_DRIVER_TYPE_HARDWARE,
+            Default::default(),
+            D3D11_CREATE_DEVICE_FLAG(0),
+            Some(&feature_levels),
+            D3D11_SDK_VERSION,
+            Some(&sd),
+            Some(&mut swap_chain),
+            Some(&mut device),
+            Some(&mut got_level),
+            Some(&mut ctx),
+        )?;
+
+        let device = device.unwrap();
+        let ctx = ctx.unwrap();
+        let swap_chain = swap_chain.unwrap();
+
+        let backbuf = swap_chain.GetBuffer::<ID3D11Texture2D>(0)?;
+
+        let rtv = {
+            let mut rtv: Option<ID3D11RenderTargetView> = None;
+            device.CreateRenderTargetView(&backbuf, None, Some(&mut rtv))?;
+            rtv.unwrap()
+        };
+        ctx.OMSetRenderTargets(Some(&[Some(rtv.clone())]), None);
+
+        let vp = D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: virt_w as f32,
+            Height: virt_h as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        };
+        ctx.RSSetViewports(Some(&[vp]));
+
+        let sampler = {
+            let desc = D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                MinLOD: 0.0,
+                MaxLOD: f32::MAX,
+                ..Default::default()
+            };
+            let mut s: Option<ID3D11SamplerState> = None;
+            device.CreateSamplerState(&desc, Some(&mut s))?;
+            s.unwrap()
+        };
+
+        let (vs, input_layout) = {
+            let mut vs_blob: Option<ID3DBlob> = None;
+            let mut err_blob: Option<ID3DBlob> = None;
+
+            D3DCompile(
+                VS_SRC.as_ptr() as _,
+                VS_SRC.len(),
+                None,
+                None,
+                None,
+                s!("main"),
+                s!("vs_5_0"),
+                0,
+                0,
+                &mut vs_blob,
+                Some(&mut err_blob),
+            )?;
+            let vs_blob = vs_blob.unwrap();
+            let vs_blob_slice = slice::from_raw_parts(
+                vs_blob.GetBufferPointer() as *const u8,
+                vs_blob.GetBufferSize(),
+            );
+
+            let mut vs: Option<ID3D11VertexShader> = None;
+            device.CreateVertexShader(vs_blob_slice, None, Some(&mut vs))?;
+            let vs = vs.unwrap();
+
+            let layout_desc = [
+                D3D11_INPUT_ELEMENT_DESC {
+                    SemanticName: s!("POSITION"),
+                    SemanticIndex: 0,
+                    Format: DXGI_FORMAT_R32G32_FLOAT,
+                    InputSlot: 0,
+                    AlignedByteOffset: 0,
+                    InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                    InstanceDataStepRate: 0,
+                },
+                D3D11_INPUT_ELEMENT_DESC {
+                    SemanticName: s!("TEXCOORD"),
+                    SemanticIndex: 0,
+                    Format: DXGI_FORMAT_R32G32_FLOAT,
+                    InputSlot: 0,
+                    AlignedByteOffset: 8,
+                    InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                    InstanceDataStepRate: 0,
+                },
+            ];
+
+            let mut il: Option<ID3D11InputLayout> = None;
+            device.CreateInputLayout(&layout_desc, vs_blob_slice, Some(&mut il))?;
+            let il = il.unwrap();
+            (vs, il)
+        };
+
+        let vb = create_quad(&device, Transform::None)?;
+        let output_vb = create_quad(&device, transform)?;
+
+        Ok(Globals {
+            device,
+            ctx,
+            swap_chain,
+            rtv,
+            vs,
+            input_layout,
+            vb,
+            output_vb,
+            sampler,
+            outputs: Vec::new(),
+        })
+    }
+}
+
+/// A quad covering the viewport, with texture coordinates mirrored by
+/// `transform`.
+unsafe fn create_quad(
+    device: &ID3D11Device,
+    transform: Transform,
+) -> windows::core::Result<ID3D11Buffer> {
+    unsafe {
+        let (horizontal, vertical) = transform.flips();
+        let (left, right) = if horizontal { (1.0, 0.0) } else { (0.0, 1.0) };
+        let (top, bottom) = if vertical { (1.0, 0.0) } else { (0.0, 1.0) };
+
+        let vertices = [
+            SimpleVertex {
+                x: -1.0,
+                y: 1.0,
+                u: left,
+                v: top,
+            },
+            SimpleVertex {
+                x: 1.0,
+                y: 1.0,
+                u: right,
+                v: top,
+            },
+            SimpleVertex {
+                x: -1.0,
+                y: -1.0,
+                u: left,
+                v: bottom,
+            },
+            SimpleVertex {
+                x: 1.0,
+                y: -1.0,
+                u: right,
+                v: bottom,
+            },
+        ];
+        let bd = D3D11_BUFFER_DESC {
+            ByteWidth: size_of::<SimpleVertex>() as u32 * 4,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_VERTEX_BUFFER.0 as u32,
+            ..Default::default()
+        };
+        let init = D3D11_SUBRESOURCE_DATA {
+            pSysMem: vertices.as_ptr() as *const c_void,
+            ..Default::default()
+        };
+        let mut buf: Option<ID3D11Buffer> = None;
+        device.CreateBuffer(&bd, Some(&init), Some(&mut buf))?;
+        Ok(buf.unwrap())
+    }
+}
+
+/// Logs the error and shows it in a notification, so mistakes in custom
+/// shaders don't go unnoticed.
+fn shader_error(message: &str) -> Error {
+    log::error!("Unable to compile shader: {}", message);
+    crate::show_notification("Screen Filter Shader Error", Some(message));
+    Error::new(E_FAIL, message)
+}
+
+unsafe fn compile_shader(
+    globals: &Globals,
+    mode: &FilterMode,
+) -> windows::core::Result<FragmentShader> {
+    unsafe {
+        let code = mode
+            .fragment_shader()
+            .map_err(|err| shader_error(&format!("Unable to read shader: {}", err)))?;
+        // Errors of custom shaders point at their file.
+        let source_name = match mode {
+            FilterMode::Custom(path) => CString::new(path.to_string_lossy().as_bytes()).ok(),
+            _ => None,
+        };
+        let source_name = source_name
+            .as_ref()
+            .map_or(PCSTR::null(), |name| PCSTR(name.as_ptr() as _));
+
+        let mut ps_blob: Option<ID3DBlob> = None;
+        let mut err_blob: Option<ID3DBlob> = None;
+
+        let compiled = D3DCompile(
+            code.as_ptr() as _,
+            code.len(),
+            source_name,
+            None,
+            None,
+            s!("main"),
+            s!("ps_5_0"),
+            0,
+            0,
+            &mut ps_blob,
+            Some(&mut err_blob),
+        );
+        if let Err(err) = compiled {
+            let message = match err_blob {
+                Some(blob) => {
+                    let log = slice::from_raw_parts(
+                        blob.GetBufferPointer() as *const u8,
+                        blob.GetBufferSize(),
+                    );
+                    String::from_utf8_lossy(log)
+                        .trim_end_matches('\0')
+                        .trim()
+                        .to_string()
+                }
+                None => err.message(),
+            };
+            return Err(shader_error(&message));
+        }
+        let ps_blob = ps_blob.unwrap();
+        let ps_blob_slice = slice::from_raw_parts(
+            ps_blob.GetBufferPointer() as *const u8,
+            ps_blob.GetBufferSize(),
+        );
+
+        let mut ps: Option<ID3D11PixelShader> = None;
+        globals
+            .device
+            .CreatePixelShader(ps_blob_slice, None, Some(&mut ps))?;
+
+        let params = shader_params(mode)
+            .iter()
+            .map(|params| create_params(globals, params))
+            .collect::<windows::core::Result<Vec<_>>>()?;
+
+        let lut = match lut_cube(mode) {
+            Some(cube) => Some((cube.clone(), create_lut(globals, cube)?)),
+            None => None,
+        };
+
+        Ok(FragmentShader {
+            ps: ps.unwrap(),
+            source: code.into_owned(),
+            params,
+            lut,
+        })
+    }
+}
+
+unsafe fn create_params(globals: &Globals, params: &[u8]) -> windows::core::Result<ID3D11Buffer> {
+    unsafe {
+        let bd = D3D11_BUFFER_DESC {
+            ByteWidth: params.len() as u32,
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_CONSTANT_BUFFER.0 as u32,
+            ..Default::default()
+        };
+        let init = D3D11_SUBRESOURCE_DATA {
+            pSysMem: params.as_ptr() as *const c_void,
+            ..Default::default()
+        };
+        let mut buf: Option<ID3D11Buffer> = None;
+        globals
+            .device
+            .CreateBuffer(&bd, Some(&init), Some(&mut buf))?;
+        Ok(buf.unwrap())
+    }
+}
+
+fn lut_cube(mode: &FilterMode) -> Option<&Arc<Cube>> {
+    match mode {
+        FilterMode::Lut(lut) => lut.cube.as_ref(),
+        _ => None,
+    }
+}
+
+/// Uploads the table of `cube` as a 3D texture, sampled with the same linear
+/// sampler as the desktop.
+unsafe fn create_lut(
+    globals: &Globals,
+    cube: &Cube,
+) -> windows::core::Result<ID3D11ShaderResourceView> {
+    unsafe {
+        let size = cube.size as u32;
+        let texels = cube
+            .table
+            .iter()
+            .map(|[r, g, b]| [*r, *g, *b, 1.0])
+            .collect::<Vec<[f32; 4]>>();
+
+        let desc = D3D11_TEXTURE3D_DESC {
+            Width: size,
+            Height: size,
+            Depth: size,
+            MipLevels: 1,
+            Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+            Usage: D3D11_USAGE_IMMUTABLE,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+            ..Default::default()
+        };
+        let init = D3D11_SUBRESOURCE_DATA {
+            pSysMem: texels.as_ptr() as *const c_void,
+            SysMemPitch: size * size_of::<[f32; 4]>() as u32,
+            SysMemSlicePitch: size * size * size_of::<[f32; 4]>() as u32,
+        };
+        let mut tex: Option<ID3D11Texture3D> = None;
+        globals
+            .device
+            .CreateTexture3D(&desc, Some(&init), Some(&mut tex))?;
+
+        let mut srv: Option<ID3D11ShaderResourceView> = None;
+        globals
+            .device
+            .CreateShaderResourceView(&tex.unwrap(), None, Some(&mut srv))?;
+        Ok(srv.unwrap())
+    }
+}
+
+unsafe fn init_duplications(g: &mut Globals) -> windows::core::Result<()> {
+    unsafe {
+        g.outputs.clear();
+
+        let mut dxgi_device: Option<IDXGIDevice> = None;
+        let _ = g
+            .device
+            .query(&IDXGIDevice::IID, std::mem::transmute(&mut dxgi_device));
+        let dxgi_device = dxgi_device.unwrap();
+
+        let adapter = dxgi_device.GetParent::<IDXGIAdapter>()?;
+
+        let mut i = 0;
+        while let Ok(output) = adapter.EnumOutputs(i) {
+            let mut output1: Option<IDXGIOutput1> = None;
+            let _ = output.query(&IDXGIOutput1::IID, std::mem::transmute(&mut output1));
+            let output1 = output1.unwrap();
+
+            let outdesc = output.GetDesc()?;
+
+            let dup = output1.DuplicateOutput(&g.device)?;
+            let dud = dup.GetDesc();
+
+            let desc = D3D11_TEXTURE2D_DESC {
+                Width: dud.ModeDesc.Width,
+                Height: dud.ModeDesc.Height,
+                MipLevels: 1,
+                ArraySize: 1,
+                Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Usage: D3D11_USAGE_DEFAULT,
+                BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0) as u32,
+                ..Default::default()
+            };
+            let mut tex: Option<ID3D11Texture2D> = None;
+            g.device.CreateTexture2D(&desc, None, Some(&mut tex))?;
+            let tex = tex.unwrap();
+
+            let srv_desc = D3D11_SHADER_RESOURCE_VIEW_DESC {
+                Format: desc.Format,
+                ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
+                Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2D: D3D11_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                    },
+                },
+            };
+            let mut srv: Option<ID3D11ShaderResourceView> = None;
+            g.device
+                .CreateShaderResourceView(&tex, Some(&srv_desc), Some(&mut srv))?;
+            let srv = srv.unwrap();
+
+            g.outputs.push(OutputDup {
+                dup,
+                desktop_rect: outdesc.DesktopCoordinates,
+                width: dud.ModeDesc.Width,
+                height: dud.ModeDesc.Height,
+                dest_tex: tex,
+                dest_srv: srv,
+                scratch: Vec::new(),
+            });
+
+            i += 1;
+        }
+
+        if g.outputs.is_empty() {
+            Err(E_FAIL.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Gives every output the intermediate targets a pipeline of `draws` needs,
+/// the last draw renders to the overlay directly.
+unsafe fn create_scratch(g: &mut Globals, draws: usize) -> windows::core::Result<()> {
+    unsafe {
+        let count = draws.saturating_sub(1).min(2);
+        for od in &mut g.outputs {
+            od.scratch.truncate(count);
+            while od.scratch.len() < count {
+                let desc = D3D11_TEXTURE2D_DESC {
+                    Width: od.width,
+                    Height: od.height,
+                    MipLevels: 1,
+                    ArraySize: 1,
+                    Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                    SampleDesc: DXGI_SAMPLE_DESC {
+                        Count: 1,
+                        Quality: 0,
+                    },
+                    Usage: D3D11_USAGE_DEFAULT,
+                    BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0) as u32,
+                    ..Default::default()
+                };
+                let mut tex: Option<ID3D11Texture2D> = None;
+                g.device.CreateTexture2D(&desc, None, Some(&mut tex))?;
+                let tex = tex.unwrap();
+
+                let mut rtv: Option<ID3D11RenderTargetView> = None;
+                g.device
+                    .CreateRenderTargetView(&tex, None, Some(&mut rtv))?;
+                let mut srv: Option<ID3D11ShaderResourceView> = None;
+                g.device
+                    .CreateShaderResourceView(&tex, None, Some(&mut srv))?;
+
+                od.scratch.push(RenderTarget {
+                    rtv: rtv.unwrap(),
+                    srv: srv.unwrap(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+unsafe fn capture_desktop(
+    g: &Globals,
+    od: &OutputDup,
+) -> std::result::Result<Vec<Rect>, CaptureError> {
+    unsafe {
+        let mut frame_info: DXGI_OUTDUPL_FRAME_INFO = zeroed();
+        let mut desktop_res: Option<IDXGIResource> = None;
+
+        match od
+            .dup
+            .AcquireNextFrame(0, &mut frame_info, &mut desktop_res)
+        {
+            Ok(_) => {}
+            Err(err) => {
+                let code = err.code().0 as i32;
+                if code == DXGI_ERROR_WAIT_TIMEOUT.0 {
+                    return Ok(Vec::new());
+                } else if code == DXGI_ERROR_ACCESS_LOST.0 {
+                    return Err(CaptureError::AccessLost);
+                } else {
+                    return Err(CaptureError::Other(err.into()));
+                }
+            }
+        };
+        let desktop_res = desktop_res.unwrap();
+
+        let mut src_tex: Option<ID3D11Texture2D> = None;
+        let _ = desktop_res.query(&ID3D11Texture2D::IID, std::mem::transmute(&mut src_tex));
+        let src_tex = src_tex.unwrap();
+
+        let mut src_desc: D3D11_TEXTURE2D_DESC = Default::default();
+        src_tex.GetDesc(&mut src_desc);
+
+        let dest_fmt = DXGI_FORMAT_B8G8R8A8_UNORM;
+
+        if src_desc.Format == dest_fmt && src_desc.Width == od.width && src_desc.Height == od.height
+        {
+            let box_ = D3D11_BOX {
+                left: 0,
+                top: 0,
+                front: 0,
+                right: src_desc.Width,
+                bottom: src_desc.Height,
+                back: 1,
+            };
+            g.ctx
+                .CopySubresourceRegion(&od.dest_tex, 0, 0, 0, 0, &src_tex, 0, Some(&box_));
+        } else if (src_desc.Format == DXGI_FORMAT_R8G8B8A8_UNORM
+            || src_desc.Format == DXGI_FORMAT_B8G8R8A8_UNORM)
+            && src_desc.Width == od.width
+            && src_desc.Height == od.height
+        {
+            let mut staging_desc = src_desc.clone();
+            staging_desc.Usage = D3D11_USAGE_STAGING;
+            staging_desc.BindFlags = 0;
+            staging_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ.0 as u32;
+            staging_desc.MiscFlags = 0;
+            staging_desc.SampleDesc.Count = 1;
+
+            let mut staging: Option<ID3D11Texture2D> = None;
+            if g.device
+                .CreateTexture2D(&staging_desc, None, Some(&mut staging))
+                .is_ok()
+            {
+                let staging = staging.unwrap();
+                g.ctx.CopyResource(&staging, &src_tex);
+
+                let mut mapped: D3D11_MAPPED_SUBRESOURCE = zeroed();
+                if g.ctx
+                    .Map(&staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))
+                    .is_ok()
+                {
+                    let row_bytes = od.width as usize * 4;
+                    let total = row_bytes * od.height as usize;
+                    let mut full = vec![0u8; total];
+
+                    let src_is_rgba = src_desc.Format == DXGI_FORMAT_R8G8B8A8_UNORM;
+                    for y in 0..od.height {
+                        let srow =
+                            (mapped.pData as *const u8).add(y as usize * mapped.RowPitch as usize);
+                        let drow = full.as_mut_ptr().add(y as usize * row_bytes);
+                        if src_is_rgba {
+                            for x in 0..od.width {
+                                let r = *srow.add(4 * x as usize + 0);
+                                let g = *srow.add(4 * x as usize + 1);
+                                let b = *srow.add(4 * x as usize + 2);
+                                let a = *srow.add(4 * x as usize + 3);
+                                *drow.add(4 * x as usize + 0) = b;
+                                *drow.add(4 * x as usize + 1) = g;
+                                *drow.add(4 * x as usize + 2) = r;
+                                *drow.add(4 * x as usize + 3) = a;
+                            }
+                        } else {
+                            std::ptr::copy_nonoverlapping(srow, drow, row_bytes);
+                        }
+                    }
+                    g.ctx.Unmap(&staging, 0);
+
+                    g.ctx.UpdateSubresource(
+                        &od.dest_tex,
+                        0,
+                        None,
+                        full.as_ptr() as *const c_void,
+                        row_bytes as u32,
+                        0,
+                    );
+                }
+            }
+        } else {
+        }
+
+        let dirty = dirty_rects(od, &frame_info);
+
+        let _ = od.dup.ReleaseFrame();
+
+        Ok(dirty)
+    }
+}
+
+/// Returns the regions of the acquired frame that changed, in desktop coordinates.
+unsafe fn dirty_rects(od: &OutputDup, frame_info: &DXGI_OUTDUPL_FRAME_INFO) -> Vec<Rect> {
+    unsafe {
+        // Only the mouse moved.
+        if frame_info.LastPresentTime == 0 {
+            return Vec::new();
+        }
+
+        let mut rects =
+            vec![RECT::default(); frame_info.TotalMetadataBufferSize as usize / size_of::<RECT>()];
+        let mut required = 0u32;
+        let dirty = if od
+            .dup
+            .GetFrameDirtyRects(
+                (rects.len() * size_of::<RECT>()) as u32,
+                rects.as_mut_ptr(),
+                &mut required,
+            )
+            .is_ok()
+        {
+            rects.truncate(required as usize / size_of::<RECT>());
+            rects
+        } else {
+            Vec::new()
+        };
+
+        // Moved regions aren't reported as dirty, fall back to the whole output.
+        if dirty.is_empty() {
+            return vec![rect_from_win(od.desktop_rect)];
+        }
+
+        dirty
+            .into_iter()
+            .map(|rect| {
+                rect_from_win(RECT {
+                    left: rect.left + od.desktop_rect.left,
+                    top: rect.top + od.desktop_rect.top,
+                    right: rect.right + od.desktop_rect.left,
+                    bottom: rect.bottom + od.desktop_rect.top,
+                })
+            })
+            .collect()
+    }
+}
+
+unsafe fn render(g: &Globals, frags: &[FragmentShader]) {
+    unsafe {
+        let clear = [0.0f32, 0.0, 0.0, 0.0];
+        g.ctx.ClearRenderTargetView(&g.rtv, &clear);
+
+        g.ctx.VSSetShader(&g.vs, None);
+        g.ctx.PSSetSamplers(0, Some(&[Some(g.sampler.clone())]));
+        g.ctx.IASetInputLayout(&g.input_layout);
+
+        let stride = size_of::<SimpleVertex>() as u32;
+        let offset = 0u32;
+        g.ctx
+            .IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+
+        let virt_left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let virt_top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+        let virt_w = GetSystemMetrics(SM_CXVIRTUALSCREEN);
+        let virt_h = GetSystemMetrics(SM_CYVIRTUALSCREEN);
+
+        let draws = draws(frags);
+        for od in &g.outputs {
+            let mut input = od.dest_srv.clone();
+
+            for (i, (frag, params)) in draws.iter().enumerate() {
+                // Only the first draw is mirrored, so each output flips in
+                // place and later passes, like the heatmap legend, stay upright.
+                let vb = if i == 0 { &g.output_vb } else { &g.vb };
+                let (rtv, vp) = if i + 1 == draws.len() {
+                    let vp = D3D11_VIEWPORT {
+                        TopLeftX: (od.desktop_rect.left - virt_left) as f32,
+                        TopLeftY: (od.desktop_rect.top - virt_top) as f32,
+                        Width: od.width as f32,
+                        Height: od.height as f32,
+                        MinDepth: 0.0,
+                        MaxDepth: 1.0,
+                    };
+                    (&g.rtv, vp)
+                } else {
+                    let vp = D3D11_VIEWPORT {
+                        TopLeftX: 0.0,
+                        TopLeftY: 0.0,
+                        Width: od.width as f32,
+                        Height: od.height as f32,
+                        MinDepth: 0.0,
+                        MaxDepth: 1.0,
+                    };
+                    (&od.scratch[i % 2].rtv, vp)
+                };
+                g.ctx.OMSetRenderTargets(Some(&[Some(rtv.clone())]), None);
+                g.ctx.RSSetViewports(Some(&[vp]));
+                g.ctx.IASetVertexBuffers(
+                    0,
+                    1,
+                    Some(&Some(vb.clone())),
+                    Some(&stride),
+                    Some(&offset),
+                );
+
+                g.ctx.PSSetShader(&frag.ps, None);
+                g.ctx.PSSetConstantBuffers(0, Some(&[params.cloned()]));
+                g.ctx.PSSetShaderResources(
+                    0,
+                    Some(&[
+                        Some(input.clone()),
+                        frag.lut.as_ref().map(|(_, lut)| lut.clone()),
+                    ]),
+                );
+
+                g.ctx.Draw(4, 0);
+
+                // The target is read by the next draw.
+                g.ctx.PSSetShaderResources(0, Some(&[None, None]));
+                if i + 1 < draws.len() {
+                    input = od.scratch[i % 2].srv.clone();
+                }
+            }
+        }
+
+        let full = D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: virt_w as f32,
+            Height: virt_h as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        };
+        g.ctx.RSSetViewports(Some(&[full]));
+
+        let _ = g.swap_chain.Present(0, DXGI_PRESENT::default());
+    }
+}
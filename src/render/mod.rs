@@ -0,0 +1,1110 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, TryRecvError};
+
+use crate::color;
+use crate::config::{Filter, FilterMode, HUE_STEP, Transform};
+
+#[cfg(windows)]
+mod dxgi;
+#[cfg(target_os = "linux")]
+pub mod wayland;
+#[cfg(target_os = "linux")]
+pub mod x11;
+
+#[cfg(windows)]
+pub use dxgi::DxgiBackend;
+#[cfg(target_os = "linux")]
+pub use wayland::WaylandBackend;
+#[cfg(target_os = "linux")]
+pub use x11::X11Backend;
+
+pub static ENABLED: AtomicBool = AtomicBool::new(false);
+pub static FROZEN: AtomicBool = AtomicBool::new(false);
+/// Hotkey presses rotating and widening [`FilterMode::Hue`] passes, in
+/// [`HUE_STEP`]s.
+pub static HUE_SHIFT: AtomicI32 = AtomicI32::new(0);
+pub static HUE_WIDEN: AtomicI32 = AtomicI32::new(0);
+/// Set by the contrast pick hotkey until the render loop picked the color.
+pub static PICK: AtomicBool = AtomicBool::new(false);
+/// Index of the active [`Filter`], 0 for the filter of the config and then
+/// one per profile.
+pub static PROFILE: AtomicUsize = AtomicUsize::new(0);
+/// Presses of the next and previous mode hotkeys, moving [`PROFILE`].
+pub static CYCLE: AtomicI32 = AtomicI32::new(0);
+/// [`PROFILE`] before a held profile hotkey, restored on release.
+static HELD_FROM: AtomicUsize = AtomicUsize::new(0);
+
+/// How long the name of the filter shows after switching.
+const INDICATOR_DURATION: Duration = Duration::from_millis(1500);
+/// Size of the indicator, centered at the top of the primary output with
+/// its height as the margin.
+pub const INDICATOR_SIZE: (u16, u16) = (360, 32);
+
+/// What a hotkey does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Toggle,
+    Freeze,
+    HueNext,
+    HuePrev,
+    HueWider,
+    HueNarrower,
+    ContrastPick,
+    /// Switches to the profile with the index in [`PROFILE`], or back if
+    /// it's active.
+    Profile(usize),
+    NextMode,
+    PrevMode,
+    Reset,
+}
+
+impl Action {
+    pub fn run(self) {
+        match self {
+            Action::Toggle => {
+                ENABLED.fetch_xor(true, Ordering::Relaxed);
+            }
+            Action::Freeze => {
+                FROZEN.fetch_xor(true, Ordering::Relaxed);
+            }
+            Action::HueNext => {
+                HUE_SHIFT.fetch_add(1, Ordering::Relaxed);
+            }
+            Action::HuePrev => {
+                HUE_SHIFT.fetch_sub(1, Ordering::Relaxed);
+            }
+            Action::HueWider => {
+                HUE_WIDEN.fetch_add(1, Ordering::Relaxed);
+            }
+            Action::HueNarrower => {
+                HUE_WIDEN.fetch_sub(1, Ordering::Relaxed);
+            }
+            Action::ContrastPick => PICK.store(true, Ordering::Relaxed),
+            Action::Profile(index) => {
+                let _ = PROFILE.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |active| {
+                    Some(if active == index { 0 } else { index })
+                });
+            }
+            Action::NextMode => {
+                CYCLE.fetch_add(1, Ordering::Relaxed);
+            }
+            Action::PrevMode => {
+                CYCLE.fetch_sub(1, Ordering::Relaxed);
+            }
+            Action::Reset => {
+                CYCLE.store(0, Ordering::Relaxed);
+                PROFILE.store(0, Ordering::Relaxed);
+                HUE_SHIFT.store(0, Ordering::Relaxed);
+                HUE_WIDEN.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Called when the keys of a held hotkey go down, [`Action::release`]
+    /// undoes it.
+    pub fn hold(self) {
+        match self {
+            Action::Toggle => ENABLED.store(true, Ordering::Relaxed),
+            Action::Freeze => FROZEN.store(true, Ordering::Relaxed),
+            Action::Profile(index) => {
+                HELD_FROM.store(PROFILE.swap(index, Ordering::Relaxed), Ordering::Relaxed)
+            }
+            action => action.run(),
+        }
+    }
+
+    pub fn release(self) {
+        match self {
+            Action::Toggle => ENABLED.store(false, Ordering::Relaxed),
+            Action::Freeze => FROZEN.store(false, Ordering::Relaxed),
+            Action::Profile(_) => {
+                PROFILE.store(HELD_FROM.load(Ordering::Relaxed), Ordering::Relaxed)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A rectangle in desktop coordinates, `right` and `bottom` are exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputInfo {
+    pub desktop_rect: Rect,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    /// The output can't be captured anymore, e.g. after a mode change or a
+    /// desktop switch. The source has to be reinitialized.
+    AccessLost,
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::AccessLost => write!(f, "Access to the output was lost"),
+            CaptureError::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl Transform {
+    /// Whether the image is mirrored horizontally and vertically.
+    pub fn flips(&self) -> (bool, bool) {
+        match self {
+            Transform::None => (false, false),
+            Transform::FlipHorizontal => (true, false),
+            Transform::FlipVertical => (false, true),
+            Transform::Rotate180 => (true, true),
+        }
+    }
+
+    /// Transforms a tightly packed RGBA8 image in place.
+    pub fn apply_cpu(&self, pixels: &mut [u8], width: u32, height: u32) {
+        let row = width as usize * 4;
+        let height = height as usize;
+        let (horizontal, vertical) = self.flips();
+
+        if vertical {
+            for y in 0..height / 2 {
+                let (top, bottom) = pixels.split_at_mut((height - 1 - y) * row);
+                top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+            }
+        }
+        if horizontal {
+            for line in pixels.chunks_exact_mut(row) {
+                line.as_chunks_mut::<4>().0.reverse();
+            }
+        }
+    }
+}
+
+/// Grabs the desktop image of each output.
+pub trait CaptureSource {
+    fn outputs(&self) -> Vec<OutputInfo>;
+
+    /// Acquires the newest frame of `output` and returns the regions that
+    /// changed since the last one, empty if nothing changed.
+    fn acquire_frame(&mut self, output: usize) -> Result<Vec<Rect>, CaptureError>;
+
+    /// Enumerates the outputs again, called after [`CaptureError::AccessLost`].
+    fn reinit(&mut self) -> Result<(), CaptureError>;
+
+    /// Cursor position in desktop coordinates, if the platform tells.
+    fn cursor(&self) -> Option<(i32, i32)>;
+
+    /// sRGB color at `(x, y)` in desktop coordinates of the last acquired
+    /// frames, `None` outside of every output.
+    fn pixel(&self, x: i32, y: i32) -> Option<[u8; 3]>;
+}
+
+/// Shows the filtered frames on top of the desktop.
+pub trait Presenter {
+    fn set_visible(&mut self, visible: bool);
+
+    /// Hides the overlay from screen captures, including our own.
+    fn set_exclude_from_capture(&mut self, exclude: bool);
+
+    /// Switches the filter passes without recreating anything else.
+    fn set_pipeline(&mut self, passes: Vec<FilterMode>);
+
+    /// Mirrors the filtered image differently.
+    fn set_transform(&mut self, transform: Transform);
+
+    /// Shows `text` briefly on top of everything, or hides it.
+    fn set_indicator(&mut self, text: Option<&str>);
+
+    /// Filters the last acquired frames and puts them on screen.
+    fn present(&mut self);
+}
+
+/// State of the render loop, independent of the backend.
+pub struct RenderLoop {
+    visible: bool,
+    frozen: bool,
+    frame_time: Duration,
+    last_render: Option<Instant>,
+    /// Passes as configured, before the hue hotkeys are applied.
+    passes: Vec<FilterMode>,
+    /// [`HUE_SHIFT`] and [`HUE_WIDEN`] applied to `passes`.
+    hue_offset: (i32, i32),
+    /// First color of a contrast pick.
+    picked: Option<[u8; 3]>,
+    /// The filter of the config and of each profile.
+    filters: Vec<Filter>,
+    /// Index of the active one in `filters`.
+    profile: usize,
+    /// When the indicator of the last switch is hidden.
+    indicator_until: Option<Instant>,
+}
+
+impl RenderLoop {
+    pub fn new(backend: &mut impl Presenter, refresh_rate: u32) -> Self {
+        backend.set_visible(false);
+        backend.set_exclude_from_capture(true);
+
+        Self {
+            visible: false,
+            frozen: false,
+            frame_time: Duration::from_secs_f64(1.0 / refresh_rate.max(1) as f64),
+            last_render: None,
+            passes: Vec::new(),
+            hue_offset: (0, 0),
+            picked: None,
+            filters: Vec::new(),
+            profile: 0,
+            indicator_until: None,
+        }
+    }
+
+    /// Switches the filters of the running loop, staying on the active
+    /// profile if it's still there.
+    pub fn set_filters<B: CaptureSource + Presenter>(
+        &mut self,
+        backend: &mut B,
+        filters: Vec<Filter>,
+    ) {
+        // Profiles may have been added or removed, the active one is found by
+        // its name. Unnamed ones stay while the count does.
+        let active = self.filters.get(self.profile);
+        self.profile = match active.and_then(|f| f.name.as_ref()) {
+            Some(name) => filters
+                .iter()
+                .position(|f| f.name.as_ref() == Some(name))
+                .unwrap_or(0),
+            None if filters.len() == self.filters.len() => self.profile,
+            None => 0,
+        };
+        self.filters = filters;
+        self.update_filter(backend);
+    }
+
+    /// Switches to the filter at `profile` in the ones set, falling back to
+    /// the first.
+    pub fn set_profile<B: CaptureSource + Presenter>(&mut self, backend: &mut B, profile: usize) {
+        let profile = if profile < self.filters.len() {
+            profile
+        } else {
+            0
+        };
+        if self.profile == profile {
+            return;
+        }
+        self.profile = profile;
+        self.update_filter(backend);
+    }
+
+    /// The index `step` filters away from `profile`, wrapping around.
+    pub fn cycled(&self, profile: usize, step: i32) -> usize {
+        (profile as i32 + step).rem_euclid(self.filters.len().max(1) as i32) as usize
+    }
+
+    fn update_filter<B: CaptureSource + Presenter>(&mut self, backend: &mut B) {
+        let Some(filter) = self
+            .filters
+            .get(self.profile)
+            .or(self.filters.first())
+            .cloned()
+        else {
+            return;
+        };
+        let label = filter.label();
+        log::info!("Switching to {}", label);
+        backend.set_indicator(Some(&label));
+        self.indicator_until = Some(Instant::now() + INDICATOR_DURATION);
+        backend.set_transform(filter.transform);
+        self.set_pipeline(backend, filter.passes);
+    }
+
+    /// Switches the filter of the running loop, a frozen frame is filtered
+    /// again right away.
+    pub fn set_pipeline<B: CaptureSource + Presenter>(
+        &mut self,
+        backend: &mut B,
+        passes: Vec<FilterMode>,
+    ) {
+        self.passes = passes;
+        self.update_pipeline(backend);
+    }
+
+    /// Rotates and widens the range of every [`FilterMode::Hue`] pass by
+    /// `shift` and `widen` [`HUE_STEP`]s on top of the configured one.
+    pub fn set_hue_offset<B: CaptureSource + Presenter>(
+        &mut self,
+        backend: &mut B,
+        shift: i32,
+        widen: i32,
+    ) {
+        if self.hue_offset == (shift, widen) {
+            return;
+        }
+        self.hue_offset = (shift, widen);
+        if self.passes.iter().any(|p| matches!(p, FilterMode::Hue(_))) {
+            self.update_pipeline(backend);
+        }
+    }
+
+    /// Picks the color under the cursor. Every second pick returns both
+    /// colors, the first one taken as the text.
+    pub fn pick<B: CaptureSource + Presenter>(
+        &mut self,
+        backend: &mut B,
+    ) -> Option<([u8; 3], [u8; 3])> {
+        // A shown frozen frame is what's on screen, capturing again would pick
+        // the filtered overlay and replace the frame with it.
+        if !(self.visible && self.frozen) {
+            // The frames can be outdated while hidden. Only the overlay is
+            // kept out, even if frozen.
+            if self.frozen {
+                backend.set_exclude_from_capture(true);
+            }
+            for output in 0..backend.outputs().len() {
+                let _ = backend.acquire_frame(output);
+            }
+            if self.frozen {
+                backend.set_exclude_from_capture(false);
+            }
+            // The changes were taken by this capture.
+            self.last_render = None;
+        }
+
+        let Some(color) = backend.cursor().and_then(|(x, y)| backend.pixel(x, y)) else {
+            log::error!("Unable to pick the color under the cursor.");
+            return None;
+        };
+        match self.picked.take() {
+            Some(text) => Some((text, color)),
+            None => {
+                log::info!("Picked {}, pick the background next.", hex(color));
+                self.picked = Some(color);
+                None
+            }
+        }
+    }
+
+    fn update_pipeline<B: CaptureSource + Presenter>(&mut self, backend: &mut B) {
+        let (shift, widen) = self.hue_offset;
+        let passes: Vec<FilterMode> = self
+            .passes
+            .iter()
+            .map(|pass| match pass {
+                FilterMode::Hue(hue) => {
+                    FilterMode::Hue(hue.adjusted(shift as f32 * HUE_STEP, widen as f32 * HUE_STEP))
+                }
+                pass => pass.clone(),
+            })
+            .collect();
+
+        log::info!("Switching filter to {:?}", passes);
+        backend.set_pipeline(passes);
+        if self.visible && self.frozen {
+            backend.present();
+        }
+        self.last_render = None;
+    }
+
+    /// Applies the `enabled` and `frozen` state and renders a frame when one
+    /// is due. Returns `false` if there's nothing to do until the state changes.
+    pub fn step<B: CaptureSource + Presenter>(
+        &mut self,
+        backend: &mut B,
+        enabled: bool,
+        frozen: bool,
+        now: Instant,
+    ) -> bool {
+        if self.indicator_until.is_some_and(|until| now >= until) {
+            self.indicator_until = None;
+            backend.set_indicator(None);
+        }
+
+        if self.visible != enabled {
+            self.visible = enabled;
+            if enabled {
+                log::info!("Showing filter window");
+            } else {
+                log::info!("Hiding filter window");
+            }
+            backend.set_visible(enabled);
+
+            // The frame shown while frozen has to be captured once, and any
+            // frame rendered before showing up again is outdated.
+            self.last_render = None;
+            if enabled && frozen {
+                render_frame(backend, true);
+            }
+        }
+
+        if self.frozen != frozen {
+            self.frozen = frozen;
+            if frozen {
+                log::info!("Freezing filter");
+            } else {
+                log::info!("Unfreezing filter");
+            }
+            // Screenshots of the frozen filter should contain the filter.
+            backend.set_exclude_from_capture(!frozen);
+        }
+
+        if !enabled || frozen {
+            return false;
+        }
+
+        let due = self
+            .last_render
+            .is_none_or(|last| now.duration_since(last) > self.frame_time);
+        if due {
+            render_frame(backend, self.last_render.is_none());
+            self.last_render = Some(now);
+        }
+
+        true
+    }
+}
+
+/// Captures every output and presents them, unless none of them changed and
+/// `force` is not set.
+fn render_frame<B: CaptureSource + Presenter>(backend: &mut B, force: bool) {
+    let mut changed = force;
+    let mut access_lost = false;
+
+    for output in 0..backend.outputs().len() {
+        match backend.acquire_frame(output) {
+            Ok(dirty) => changed |= !dirty.is_empty(),
+            Err(CaptureError::AccessLost) => access_lost = true,
+            Err(CaptureError::Other(_)) => {}
+        }
+    }
+
+    if access_lost {
+        log::info!("Lost access to the desktop, reinitializing capture.");
+        if let Err(err) = backend.reinit() {
+            log::error!("Unable to reinitialize capture: {}", err);
+        }
+    }
+
+    if changed {
+        backend.present();
+    }
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// WCAG 2.x ratio and APCA `Lc` of `text` on `background`.
+pub fn contrast_report(text: [u8; 3], background: [u8; 3]) -> String {
+    let [text_color, background_color] = [text, background].map(|c| c.map(|v| v as f32 / 255.0));
+    let ratio = color::contrast_ratio(
+        color::relative_luminance(text_color),
+        color::relative_luminance(background_color),
+    );
+    let lc = color::apca_contrast(text_color, background_color);
+    format!(
+        "{} on {}: {:.2}:1, Lc {:.1}",
+        hex(text),
+        hex(background),
+        ratio,
+        lc
+    )
+}
+
+/// Runs `backend`, which was created with the first of `filters`, until
+/// `terminator` fires.
+pub fn render_loop<B: CaptureSource + Presenter>(
+    mut backend: B,
+    filters: Vec<Filter>,
+    refresh_rate: u32,
+    terminator: Receiver<()>,
+    updates: Receiver<Vec<Filter>>,
+) {
+    let mut state = RenderLoop::new(&mut backend, refresh_rate);
+    state.passes = filters[0].passes.clone();
+    state.filters = filters;
+    ENABLED.store(false, Ordering::Relaxed);
+    FROZEN.store(false, Ordering::Relaxed);
+    HUE_SHIFT.store(0, Ordering::Relaxed);
+    HUE_WIDEN.store(0, Ordering::Relaxed);
+    PICK.store(false, Ordering::Relaxed);
+    PROFILE.store(0, Ordering::Relaxed);
+    CYCLE.store(0, Ordering::Relaxed);
+
+    loop {
+        let terminator = terminator.try_recv();
+        if terminator.is_ok() || terminator.is_err_and(|e| e == TryRecvError::Disconnected) {
+            log::info!("Terminating render loop.");
+            break;
+        }
+
+        while let Ok(filters) = updates.try_recv() {
+            state.set_filters(&mut backend, filters);
+        }
+        let mut profile = PROFILE.load(Ordering::Relaxed);
+        let step = CYCLE.swap(0, Ordering::Relaxed);
+        if step != 0 {
+            profile = state.cycled(profile, step);
+        }
+        state.set_profile(&mut backend, profile);
+        // Moved by a reload, or past the profiles.
+        if state.profile != profile || step != 0 {
+            PROFILE.store(state.profile, Ordering::Relaxed);
+        }
+        state.set_hue_offset(
+            &mut backend,
+            HUE_SHIFT.load(Ordering::Relaxed),
+            HUE_WIDEN.load(Ordering::Relaxed),
+        );
+        if PICK.swap(false, Ordering::Relaxed)
+            && let Some((text, background)) = state.pick(&mut backend)
+        {
+            let report = contrast_report(text, background);
+            log::info!("Contrast of {}", report);
+            crate::show_notification("Contrast", Some(&report));
+        }
+
+        let enabled = ENABLED.load(Ordering::Relaxed);
+        let frozen = FROZEN.load(Ordering::Relaxed);
+
+        if !state.step(&mut backend, enabled, frozen, Instant::now()) {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    use super::*;
+    use crate::config::Hue;
+
+    /// Records what the loop asks for, frames are queued per output.
+    #[derive(Default)]
+    struct MockBackend {
+        outputs: Vec<OutputInfo>,
+        frames: Vec<VecDeque<Result<Vec<Rect>, CaptureError>>>,
+        visible: bool,
+        excluded: bool,
+        passes: Vec<FilterMode>,
+        transform: Transform,
+        indicator: Option<String>,
+        presents: usize,
+        acquires: usize,
+        reinits: usize,
+        /// Desktop color under the cursor.
+        cursor: Option<[u8; 3]>,
+    }
+
+    const DIRTY: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 16,
+        bottom: 16,
+    };
+
+    impl MockBackend {
+        fn new(outputs: usize) -> Self {
+            let output = OutputInfo {
+                desktop_rect: Rect::default(),
+                width: 16,
+                height: 16,
+            };
+            Self {
+                outputs: vec![output; outputs],
+                frames: (0..outputs).map(|_| VecDeque::new()).collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl CaptureSource for MockBackend {
+        fn outputs(&self) -> Vec<OutputInfo> {
+            self.outputs.clone()
+        }
+
+        fn acquire_frame(&mut self, output: usize) -> Result<Vec<Rect>, CaptureError> {
+            self.acquires += 1;
+            self.frames[output].pop_front().unwrap_or(Ok(vec![DIRTY]))
+        }
+
+        fn reinit(&mut self) -> Result<(), CaptureError> {
+            self.reinits += 1;
+            Ok(())
+        }
+
+        fn cursor(&self) -> Option<(i32, i32)> {
+            self.cursor.map(|_| (0, 0))
+        }
+
+        fn pixel(&self, _x: i32, _y: i32) -> Option<[u8; 3]> {
+            self.cursor
+        }
+    }
+
+    impl Presenter for MockBackend {
+        fn set_visible(&mut self, visible: bool) {
+            self.visible = visible;
+        }
+
+        fn set_exclude_from_capture(&mut self, exclude: bool) {
+            self.excluded = exclude;
+        }
+
+        fn set_pipeline(&mut self, passes: Vec<FilterMode>) {
+            self.passes = passes;
+        }
+
+        fn set_transform(&mut self, transform: Transform) {
+            self.transform = transform;
+        }
+
+        fn set_indicator(&mut self, text: Option<&str>) {
+            self.indicator = text.map(String::from);
+        }
+
+        fn present(&mut self) {
+            self.presents += 1;
+        }
+    }
+
+    #[test]
+    fn transform() {
+        // 2x2, one channel value per pixel.
+        let image = [1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0];
+        for (transform, expected) in [
+            (Transform::None, [1, 2, 3, 4]),
+            (Transform::FlipHorizontal, [2, 1, 4, 3]),
+            (Transform::FlipVertical, [3, 4, 1, 2]),
+            (Transform::Rotate180, [4, 3, 2, 1]),
+        ] {
+            let mut pixels = image;
+            transform.apply_cpu(&mut pixels, 2, 2);
+            let firsts: Vec<u8> = pixels.chunks(4).map(|px| px[0]).collect();
+            assert_eq!(firsts, expected, "{:?}", transform);
+        }
+
+        // Odd sizes keep the middle in place.
+        let mut pixels: Vec<u8> = (1..=3).flat_map(|v| [v, 0, 0, 0]).collect();
+        Transform::FlipVertical.apply_cpu(&mut pixels, 1, 3);
+        assert_eq!(
+            pixels.chunks(4).map(|px| px[0]).collect::<Vec<_>>(),
+            [3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn starts_hidden_and_excluded() {
+        let mut backend = MockBackend::new(1);
+        backend.visible = true;
+        let mut state = RenderLoop::new(&mut backend, 60);
+        assert!(!backend.visible);
+        assert!(backend.excluded);
+
+        assert!(!state.step(&mut backend, false, false, Instant::now()));
+        assert_eq!(backend.presents, 0);
+    }
+
+    #[test]
+    fn enable_and_disable() {
+        let mut backend = MockBackend::new(2);
+        let mut state = RenderLoop::new(&mut backend, 60);
+        let now = Instant::now();
+
+        assert!(state.step(&mut backend, true, false, now));
+        assert!(backend.visible);
+        assert_eq!(backend.presents, 1);
+
+        assert!(!state.step(&mut backend, false, false, now));
+        assert!(!backend.visible);
+        assert_eq!(backend.presents, 1);
+    }
+
+    #[test]
+    fn freeze() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 60);
+        let now = Instant::now();
+
+        state.step(&mut backend, true, false, now);
+        assert!(!state.step(&mut backend, true, true, now + Duration::from_secs(1)));
+        assert!(!backend.excluded);
+        assert_eq!(backend.presents, 1);
+
+        // Showing the frozen filter captures it once.
+        state.step(&mut backend, false, true, now + Duration::from_secs(2));
+        state.step(&mut backend, true, true, now + Duration::from_secs(3));
+        assert!(backend.visible);
+        assert_eq!(backend.presents, 2);
+
+        state.step(&mut backend, true, false, now + Duration::from_secs(4));
+        assert!(backend.excluded);
+        assert_eq!(backend.presents, 3);
+    }
+
+    #[test]
+    fn set_pipeline() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let now = Instant::now();
+
+        state.step(&mut backend, true, false, now);
+        state.set_pipeline(&mut backend, vec![FilterMode::LabGrayscale]);
+        assert!(matches!(
+            backend.passes.as_slice(),
+            [FilterMode::LabGrayscale]
+        ));
+        assert_eq!(backend.presents, 1);
+        // The next frame is due right away.
+        state.step(&mut backend, true, false, now + Duration::from_millis(10));
+        assert_eq!(backend.presents, 2);
+
+        // A frozen frame is filtered again without capturing.
+        state.step(&mut backend, true, true, now + Duration::from_secs(1));
+        backend.frames[0].push_back(Err(CaptureError::AccessLost));
+        state.set_pipeline(
+            &mut backend,
+            vec![FilterMode::LabGrayscale, FilterMode::OklabGrayscale],
+        );
+        assert_eq!(backend.presents, 3);
+        assert_eq!(backend.frames[0].len(), 1);
+    }
+
+    #[test]
+    fn profiles() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let filter = |name: Option<&str>, mode, transform| Filter {
+            name: name.map(String::from),
+            passes: vec![mode],
+            transform,
+        };
+        state.set_filters(
+            &mut backend,
+            vec![
+                filter(None, FilterMode::LabGrayscale, Transform::None),
+                filter(Some("a"), FilterMode::OklabGrayscale, Transform::Rotate180),
+            ],
+        );
+        assert!(matches!(
+            backend.passes.as_slice(),
+            [FilterMode::LabGrayscale]
+        ));
+
+        state.set_profile(&mut backend, 1);
+        assert!(matches!(
+            backend.passes.as_slice(),
+            [FilterMode::OklabGrayscale]
+        ));
+        assert_eq!(backend.transform, Transform::Rotate180);
+
+        // Reloading keeps the profile, unless it's gone.
+        state.set_filters(
+            &mut backend,
+            vec![
+                filter(None, FilterMode::LabGrayscale, Transform::None),
+                filter(Some("a"), FilterMode::LabGrayscale, Transform::FlipVertical),
+            ],
+        );
+        assert_eq!(backend.transform, Transform::FlipVertical);
+        state.set_filters(
+            &mut backend,
+            vec![filter(None, FilterMode::OklabGrayscale, Transform::None)],
+        );
+        assert!(matches!(
+            backend.passes.as_slice(),
+            [FilterMode::OklabGrayscale]
+        ));
+        assert_eq!(backend.transform, Transform::None);
+        assert_eq!(state.profile, 0);
+
+        // The active profile is followed when one before it is removed.
+        let profiles = |names: &[&str]| {
+            let mut filters = vec![filter(None, FilterMode::LabGrayscale, Transform::None)];
+            filters.extend(names.iter().map(|name| {
+                let transform = if *name == "b" {
+                    Transform::FlipHorizontal
+                } else {
+                    Transform::None
+                };
+                filter(Some(name), FilterMode::OklabGrayscale, transform)
+            }));
+            filters
+        };
+        state.set_filters(&mut backend, profiles(&["a", "b"]));
+        state.set_profile(&mut backend, 2);
+        state.set_filters(&mut backend, profiles(&["b"]));
+        assert_eq!(state.profile, 1);
+        assert_eq!(backend.transform, Transform::FlipHorizontal);
+        state.set_profile(&mut backend, 5);
+        assert_eq!(state.profile, 0);
+    }
+
+    /// Serializes the tests changing the hotkey statics.
+    static HOTKEYS: Mutex<()> = Mutex::new(());
+
+    fn lock_hotkeys() -> MutexGuard<'static, ()> {
+        let guard = HOTKEYS.lock().unwrap_or_else(PoisonError::into_inner);
+        Action::Reset.run();
+        guard
+    }
+
+    #[test]
+    fn profile_hotkeys() {
+        let _guard = lock_hotkeys();
+
+        // The hotkey of the active profile switches back.
+        Action::Profile(2).run();
+        assert_eq!(PROFILE.load(Ordering::Relaxed), 2);
+        Action::Profile(1).run();
+        assert_eq!(PROFILE.load(Ordering::Relaxed), 1);
+        Action::Profile(1).run();
+        assert_eq!(PROFILE.load(Ordering::Relaxed), 0);
+
+        HUE_SHIFT.store(3, Ordering::Relaxed);
+        Action::Profile(2).run();
+        Action::NextMode.run();
+        Action::Reset.run();
+        assert_eq!(PROFILE.load(Ordering::Relaxed), 0);
+        assert_eq!(CYCLE.load(Ordering::Relaxed), 0);
+        assert_eq!(HUE_SHIFT.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn terminate() {
+        let _guard = lock_hotkeys();
+        let (terminator_tx, terminator_rx) = crossbeam_channel::unbounded();
+        let (_updates_tx, updates_rx) = crossbeam_channel::unbounded();
+        let filters = vec![Filter {
+            name: None,
+            passes: vec![FilterMode::LabGrayscale],
+            transform: Transform::None,
+        }];
+
+        // Every thread of the loop stops once the sender is dropped.
+        let hotkeys = terminator_rx.clone();
+        let hotkeys = std::thread::spawn(move || hotkeys.recv());
+        let render = std::thread::spawn(move || {
+            render_loop(MockBackend::new(1), filters, 60, terminator_rx, updates_rx)
+        });
+        drop(terminator_tx);
+        render.join().unwrap();
+        assert!(hotkeys.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn held_hotkeys() {
+        let _guard = lock_hotkeys();
+
+        // Holding a profile hotkey previews it, releasing goes back.
+        Action::Profile(1).run();
+        Action::Profile(2).hold();
+        assert_eq!(PROFILE.load(Ordering::Relaxed), 2);
+        Action::Profile(2).release();
+        assert_eq!(PROFILE.load(Ordering::Relaxed), 1);
+
+        for (action, flag) in [(Action::Toggle, &ENABLED), (Action::Freeze, &FROZEN)] {
+            action.hold();
+            assert!(flag.load(Ordering::Relaxed));
+            action.release();
+            assert!(!flag.load(Ordering::Relaxed));
+        }
+    }
+
+    #[test]
+    fn cycle_and_indicator() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let filter = |mode| Filter {
+            name: None,
+            passes: vec![mode],
+            transform: Transform::None,
+        };
+        state.set_filters(
+            &mut backend,
+            vec![
+                filter(FilterMode::LabGrayscale),
+                filter(FilterMode::OklabGrayscale),
+                filter(FilterMode::Protanopia { severity: 1.0 }),
+            ],
+        );
+        assert_eq!(state.cycled(0, 1), 1);
+        assert_eq!(state.cycled(2, 1), 0);
+        assert_eq!(state.cycled(0, -1), 2);
+        assert_eq!(state.cycled(1, -4), 0);
+
+        let switched = Instant::now();
+        state.set_profile(&mut backend, 2);
+        assert_eq!(backend.indicator.as_deref(), Some("Protanopia"));
+        state.step(&mut backend, false, false, switched);
+        assert!(backend.indicator.is_some());
+        state.step(
+            &mut backend,
+            false,
+            false,
+            switched + Duration::from_secs(2),
+        );
+        assert_eq!(backend.indicator, None);
+    }
+
+    #[test]
+    fn hue_offset() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let hue = Hue {
+            center: 30.0,
+            width: 60.0,
+            falloff: 15.0,
+        };
+
+        // Nothing to adjust.
+        state.set_pipeline(&mut backend, vec![FilterMode::LabGrayscale]);
+        backend.passes.clear();
+        state.set_hue_offset(&mut backend, 1, 0);
+        assert!(backend.passes.is_empty());
+
+        // The offset carries over to a new pipeline.
+        state.set_pipeline(
+            &mut backend,
+            vec![FilterMode::LabGrayscale, FilterMode::Hue(hue)],
+        );
+        assert!(matches!(
+            backend.passes.as_slice(),
+            [
+                FilterMode::LabGrayscale,
+                FilterMode::Hue(Hue {
+                    center: 40.0,
+                    width: 60.0,
+                    ..
+                })
+            ]
+        ));
+        state.set_hue_offset(&mut backend, -4, 2);
+        assert!(matches!(
+            backend.passes.as_slice(),
+            [
+                _,
+                FilterMode::Hue(Hue {
+                    center: 350.0,
+                    width: 80.0,
+                    ..
+                })
+            ]
+        ));
+    }
+
+    #[test]
+    fn contrast_pick() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let now = Instant::now();
+
+        assert_eq!(state.pick(&mut backend), None);
+        backend.cursor = Some([0x76; 3]);
+        assert_eq!(state.pick(&mut backend), None);
+        backend.cursor = Some([255; 3]);
+        assert_eq!(state.pick(&mut backend), Some(([0x76; 3], [255; 3])));
+        assert_eq!(state.pick(&mut backend), None);
+
+        // Picking captured the changes, the next frame is rendered anyway.
+        backend.frames[0].push_back(Ok(Vec::new()));
+        state.step(&mut backend, true, false, now);
+        assert_eq!(backend.presents, 1);
+
+        assert_eq!(
+            contrast_report([0x76; 3], [255; 3]),
+            "#767676 on #ffffff: 4.54:1, Lc 71.6"
+        );
+    }
+
+    #[test]
+    fn contrast_pick_frozen() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let now = Instant::now();
+        backend.cursor = Some([0x76; 3]);
+
+        // The frozen frame is picked from, not captured again.
+        state.step(&mut backend, true, true, now);
+        let (acquires, presents) = (backend.acquires, backend.presents);
+        assert_eq!(state.pick(&mut backend), None);
+        assert_eq!((backend.acquires, backend.presents), (acquires, presents));
+        assert!(!backend.excluded);
+
+        // Hidden, the outdated frame is captured without the overlay.
+        state.step(&mut backend, false, true, now);
+        backend.cursor = Some([255; 3]);
+        assert_eq!(state.pick(&mut backend), Some(([0x76; 3], [255; 3])));
+        assert_eq!(backend.acquires, acquires + 1);
+        assert!(!backend.excluded);
+    }
+
+    #[test]
+    fn frame_pacing() {
+        let mut backend = MockBackend::new(1);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let now = Instant::now();
+
+        state.step(&mut backend, true, false, now);
+        state.step(&mut backend, true, false, now + Duration::from_millis(50));
+        assert_eq!(backend.presents, 1);
+        state.step(&mut backend, true, false, now + Duration::from_millis(101));
+        assert_eq!(backend.presents, 2);
+        state.step(&mut backend, true, false, now + Duration::from_millis(150));
+        assert_eq!(backend.presents, 2);
+    }
+
+    #[test]
+    fn skips_unchanged_frames() {
+        let mut backend = MockBackend::new(2);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let now = Instant::now();
+
+        state.step(&mut backend, true, false, now);
+        assert_eq!(backend.presents, 1);
+
+        backend.frames[0].push_back(Ok(Vec::new()));
+        backend.frames[1].push_back(Ok(Vec::new()));
+        state.step(&mut backend, true, false, now + Duration::from_secs(1));
+        assert_eq!(backend.presents, 1);
+
+        backend.frames[0].push_back(Ok(Vec::new()));
+        state.step(&mut backend, true, false, now + Duration::from_secs(2));
+        assert_eq!(backend.presents, 2);
+    }
+
+    #[test]
+    fn reinit_after_access_lost() {
+        let mut backend = MockBackend::new(2);
+        let mut state = RenderLoop::new(&mut backend, 10);
+        let now = Instant::now();
+
+        backend.frames[0].push_back(Err(CaptureError::AccessLost));
+        backend.frames[1].push_back(Err(CaptureError::AccessLost));
+        state.step(&mut backend, true, false, now);
+        assert_eq!(backend.reinits, 1);
+
+        state.step(&mut backend, true, false, now + Duration::from_secs(1));
+        assert_eq!(backend.reinits, 1);
+        assert_eq!(backend.presents, 2);
+    }
+}
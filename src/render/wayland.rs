@@ -0,0 +1,755 @@
+//! Wayland backend for wlroots based compositors.
+//!
+//! Outputs are captured with `wlr-screencopy` and the filtered frames are shown
+//! on `wlr-layer-shell` overlay surfaces. Screencopy can't leave a surface out,
+//! so the overlay of an output is swapped for a transparent buffer while it's
+//! captured. This flickers on some compositors, a lower refresh rate helps.
+
+use std::fs::File;
+use std::os::fd::AsFd;
+use std::os::unix::fs::FileExt;
+
+use crossbeam_channel::Receiver;
+use rustix::fs::MemfdFlags;
+use signal_hook::consts::{SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::{
+    wl_buffer::WlBuffer, wl_callback, wl_callback::WlCallback, wl_compositor::WlCompositor,
+    wl_output, wl_output::WlOutput, wl_region::WlRegion, wl_registry, wl_registry::WlRegistry,
+    wl_shm, wl_shm::WlShm, wl_shm_pool::WlShmPool, wl_surface::WlSurface,
+};
+use wayland_client::{Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum, delegate_noop};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+};
+use wayland_protocols_wlr::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+};
+
+use crate::APP_ID;
+use crate::color;
+use crate::config::{FilterMode, Transform};
+use crate::render::{Action, CaptureError, CaptureSource, OutputInfo, Presenter, Rect};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+impl From<wayland_client::DispatchError> for CaptureError {
+    fn from(err: wayland_client::DispatchError) -> Self {
+        CaptureError::Other(err.into())
+    }
+}
+
+/// A `wl_shm` buffer backed by its own memfd.
+struct Buffer {
+    file: File,
+    pool: WlShmPool,
+    buffer: WlBuffer,
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: wl_shm::Format,
+}
+
+impl Buffer {
+    fn new(
+        shm: &WlShm,
+        qh: &QueueHandle<State>,
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: wl_shm::Format,
+    ) -> Result<Self, Error> {
+        let size = stride * height;
+        let fd = rustix::fs::memfd_create(APP_ID, MemfdFlags::CLOEXEC)?;
+        rustix::fs::ftruncate(&fd, size as u64)?;
+        let pool = shm.create_pool(fd.as_fd(), size as i32, qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            width as i32,
+            height as i32,
+            stride as i32,
+            format,
+            qh,
+            (),
+        );
+
+        Ok(Self {
+            file: File::from(fd),
+            pool,
+            buffer,
+            width,
+            height,
+            stride,
+            format,
+        })
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+        self.pool.destroy();
+    }
+}
+
+struct Overlay {
+    surface: WlSurface,
+    layer: ZwlrLayerSurfaceV1,
+    configured: bool,
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        self.layer.destroy();
+        self.surface.destroy();
+    }
+}
+
+struct Output {
+    /// Name of the `wl_output` global.
+    name: u32,
+    output: WlOutput,
+    position: (i32, i32),
+    size: (u32, u32),
+    scale: i32,
+    overlay: Option<Overlay>,
+    /// Filtered frame shown on the overlay.
+    shown: Option<Buffer>,
+    /// Transparent frame shown while capturing.
+    clear: Option<Buffer>,
+    captured: Option<Buffer>,
+    /// RGBA8, filled by [`CaptureSource::acquire_frame`].
+    frame: Vec<u8>,
+}
+
+impl Output {
+    fn info(&self) -> OutputInfo {
+        let scale = self.scale.max(1);
+        let (x, y) = self.position;
+        OutputInfo {
+            desktop_rect: Rect {
+                left: x,
+                top: y,
+                right: x + self.size.0 as i32 / scale,
+                bottom: y + self.size.1 as i32 / scale,
+            },
+            width: self.size.0,
+            height: self.size.1,
+        }
+    }
+}
+
+/// Progress of the screencopy frame being captured.
+#[derive(Default)]
+struct Capture {
+    buffer: Option<(WEnum<wl_shm::Format>, u32, u32, u32)>,
+    buffer_done: bool,
+    y_invert: bool,
+    result: Option<bool>,
+}
+
+/// Everything touched by the event handlers.
+#[derive(Default)]
+struct State {
+    outputs: Vec<Output>,
+    outputs_changed: bool,
+    capture: Capture,
+    frame_done: bool,
+}
+
+impl State {
+    fn output(&mut self, name: u32) -> Option<&mut Output> {
+        self.outputs.iter_mut().find(|o| o.name == name)
+    }
+}
+
+pub struct WaylandBackend {
+    conn: Connection,
+    queue: EventQueue<State>,
+    qh: QueueHandle<State>,
+    state: State,
+    compositor: WlCompositor,
+    shm: WlShm,
+    layer_shell: ZwlrLayerShellV1,
+    screencopy: ZwlrScreencopyManagerV1,
+    passes: Vec<FilterMode>,
+    transform: Transform,
+    visible: bool,
+}
+
+impl WaylandBackend {
+    pub fn new(passes: Vec<FilterMode>, transform: Transform) -> Result<Self, Error> {
+        if !passes.iter().all(FilterMode::supports_cpu) {
+            return Err("Custom shaders are only supported on Windows".into());
+        }
+
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+
+        let compositor = globals.bind(&qh, 4..=6, ())?;
+        let shm = globals.bind(&qh, 1..=1, ())?;
+        let layer_shell = globals
+            .bind(&qh, 1..=4, ())
+            .map_err(|_| "The compositor doesn't support wlr-layer-shell")?;
+        let screencopy = globals
+            .bind(&qh, 1..=3, ())
+            .map_err(|_| "The compositor doesn't support wlr-screencopy")?;
+
+        let mut state = State::default();
+        globals.contents().with_list(|list| {
+            for global in list
+                .iter()
+                .filter(|g| g.interface == WlOutput::interface().name)
+            {
+                state.outputs.push(bind_output(
+                    globals.registry(),
+                    global.name,
+                    global.version,
+                    &qh,
+                ));
+            }
+        });
+        queue.roundtrip(&mut state)?;
+
+        Ok(Self {
+            conn,
+            queue,
+            qh,
+            state,
+            compositor,
+            shm,
+            layer_shell,
+            screencopy,
+            passes,
+            transform,
+            visible: false,
+        })
+    }
+
+    fn create_overlays(&mut self) -> Result<(), CaptureError> {
+        for output in &mut self.state.outputs {
+            if output.overlay.is_some() {
+                continue;
+            }
+
+            let surface = self.compositor.create_surface(&self.qh, ());
+            // An empty input region lets every click through.
+            let region = self.compositor.create_region(&self.qh, ());
+            surface.set_input_region(Some(&region));
+            region.destroy();
+
+            let layer = self.layer_shell.get_layer_surface(
+                &surface,
+                Some(&output.output),
+                zwlr_layer_shell_v1::Layer::Overlay,
+                APP_ID.into(),
+                &self.qh,
+                output.name,
+            );
+            layer.set_anchor(
+                zwlr_layer_surface_v1::Anchor::Top
+                    | zwlr_layer_surface_v1::Anchor::Bottom
+                    | zwlr_layer_surface_v1::Anchor::Left
+                    | zwlr_layer_surface_v1::Anchor::Right,
+            );
+            layer.set_exclusive_zone(-1);
+            layer.set_keyboard_interactivity(zwlr_layer_surface_v1::KeyboardInteractivity::None);
+            surface.commit();
+
+            output.overlay = Some(Overlay {
+                surface,
+                layer,
+                configured: false,
+            });
+        }
+
+        // Buffers may only be attached after the first configure.
+        self.queue.roundtrip(&mut self.state)?;
+        Ok(())
+    }
+
+    /// Swaps the overlay of `output` for a transparent buffer and waits until
+    /// it's on screen.
+    fn show_clear(&mut self, output: usize) -> Result<(), CaptureError> {
+        let out = &mut self.state.outputs[output];
+        let Some(overlay) = out.overlay.as_ref().filter(|o| o.configured) else {
+            return Ok(());
+        };
+        if out.clear.is_none() {
+            // Zeroed memory is transparent.
+            out.clear = Some(
+                Buffer::new(&self.shm, &self.qh, 1, 1, 4, wl_shm::Format::Argb8888)
+                    .map_err(CaptureError::Other)?,
+            );
+        }
+
+        overlay
+            .surface
+            .attach(out.clear.as_ref().map(|b| &b.buffer), 0, 0);
+        overlay.surface.set_buffer_scale(1);
+        overlay.surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        overlay.surface.frame(&self.qh, ());
+        overlay.surface.commit();
+
+        self.state.frame_done = false;
+        while !self.state.frame_done {
+            self.queue.blocking_dispatch(&mut self.state)?;
+        }
+        Ok(())
+    }
+
+    /// Copies the current content of `output`, overlay included, to its frame.
+    fn capture(&mut self, output: usize) -> Result<(), CaptureError> {
+        let frame =
+            self.screencopy
+                .capture_output(0, &self.state.outputs[output].output, &self.qh, ());
+        let version = frame.version();
+
+        self.state.capture = Capture::default();
+        while !(self.state.capture.result.is_some()
+            || self.state.capture.buffer.is_some()
+                && (version < 3 || self.state.capture.buffer_done))
+        {
+            self.queue.blocking_dispatch(&mut self.state)?;
+        }
+        let Some((WEnum::Value(format), width, height, stride)) = self.state.capture.buffer else {
+            frame.destroy();
+            return Err(CaptureError::Other("Unsupported screencopy format".into()));
+        };
+
+        let out = &mut self.state.outputs[output];
+        let reusable = out.captured.as_ref().is_some_and(|b| {
+            (b.width, b.height, b.stride, b.format) == (width, height, stride, format)
+        });
+        if !reusable {
+            out.captured = Some(
+                Buffer::new(&self.shm, &self.qh, width, height, stride, format)
+                    .map_err(CaptureError::Other)?,
+            );
+        }
+        frame.copy(&out.captured.as_ref().unwrap().buffer);
+
+        while self.state.capture.result.is_none() {
+            self.queue.blocking_dispatch(&mut self.state)?;
+        }
+        frame.destroy();
+        if self.state.capture.result != Some(true) {
+            return Err(CaptureError::Other("Screencopy failed".into()));
+        }
+
+        let y_invert = self.state.capture.y_invert;
+        let out = &mut self.state.outputs[output];
+        let buffer = out.captured.as_ref().unwrap();
+        let mut data = vec![0; (stride * height) as usize];
+        buffer
+            .file
+            .read_exact_at(&mut data, 0)
+            .map_err(|err| CaptureError::Other(err.into()))?;
+
+        let bgr = match format {
+            wl_shm::Format::Argb8888 | wl_shm::Format::Xrgb8888 => true,
+            wl_shm::Format::Abgr8888 | wl_shm::Format::Xbgr8888 => false,
+            _ => {
+                return Err(CaptureError::Other(
+                    format!("Unsupported screencopy format {:?}", format).into(),
+                ));
+            }
+        };
+
+        out.size = (width, height);
+        out.frame.resize((width * height * 4) as usize, 0);
+        let row_bytes = width as usize * 4;
+        for y in 0..height as usize {
+            let src_y = if y_invert { height as usize - 1 - y } else { y };
+            let src_row = &data[src_y * stride as usize..][..row_bytes];
+            let dst_row = &mut out.frame[y * row_bytes..][..row_bytes];
+            for (s, d) in src_row.chunks_exact(4).zip(dst_row.chunks_exact_mut(4)) {
+                if bgr {
+                    d[..3].copy_from_slice(&[s[2], s[1], s[0]]);
+                } else {
+                    d[..3].copy_from_slice(&s[..3]);
+                }
+                d[3] = 255;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn show_filtered(&mut self, output: usize) -> Result<(), Error> {
+        let out = &mut self.state.outputs[output];
+        let Some(overlay) = out.overlay.as_ref().filter(|o| o.configured) else {
+            return Ok(());
+        };
+        let (width, height) = out.size;
+        if out.frame.len() != (width * height * 4) as usize || width == 0 {
+            return Ok(());
+        }
+
+        let mut data = out.frame.clone();
+        // Mirrored first, so the heatmap legend stays upright.
+        self.transform.apply_cpu(&mut data, width, height);
+        color::apply_pipeline(&self.passes, &mut data, width, height);
+        // RGBA to little endian XRGB.
+        for px in data.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+
+        if out
+            .shown
+            .as_ref()
+            .is_none_or(|b| (b.width, b.height) != (width, height))
+        {
+            out.shown = Some(Buffer::new(
+                &self.shm,
+                &self.qh,
+                width,
+                height,
+                width * 4,
+                wl_shm::Format::Xrgb8888,
+            )?);
+        }
+        let buffer = out.shown.as_ref().unwrap();
+        buffer.file.write_all_at(&data, 0)?;
+
+        overlay.surface.attach(Some(&buffer.buffer), 0, 0);
+        overlay.surface.set_buffer_scale(out.scale.max(1));
+        overlay
+            .surface
+            .damage_buffer(0, 0, width as i32, height as i32);
+        overlay.surface.commit();
+
+        Ok(())
+    }
+}
+
+impl CaptureSource for WaylandBackend {
+    fn outputs(&self) -> Vec<OutputInfo> {
+        self.state.outputs.iter().map(Output::info).collect()
+    }
+
+    /// Screencopy only reports damage when asked for the same output twice in
+    /// a row, so the whole output is always reported.
+    fn acquire_frame(&mut self, output: usize) -> Result<Vec<Rect>, CaptureError> {
+        if self.state.outputs_changed || output >= self.state.outputs.len() {
+            return Err(CaptureError::AccessLost);
+        }
+
+        self.show_clear(output)?;
+        self.capture(output)?;
+        Ok(vec![self.state.outputs[output].info().desktop_rect])
+    }
+
+    fn reinit(&mut self) -> Result<(), CaptureError> {
+        self.queue.roundtrip(&mut self.state)?;
+        self.state.outputs_changed = false;
+        if self.visible {
+            self.create_overlays()?;
+        }
+        Ok(())
+    }
+
+    /// Wayland only tells clients about the cursor over their own surfaces,
+    /// and the overlay doesn't take input.
+    fn cursor(&self) -> Option<(i32, i32)> {
+        None
+    }
+
+    fn pixel(&self, _x: i32, _y: i32) -> Option<[u8; 3]> {
+        None
+    }
+}
+
+impl Presenter for WaylandBackend {
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if visible {
+            if let Err(err) = self.create_overlays() {
+                log::error!("Unable to create overlay: {}", err);
+            }
+        } else {
+            for output in &mut self.state.outputs {
+                output.overlay = None;
+            }
+        }
+        let _ = self.conn.flush();
+    }
+
+    /// Wayland has no way to hide a surface from screenshots, the overlay
+    /// always shows up in them.
+    fn set_exclude_from_capture(&mut self, _exclude: bool) {}
+
+    fn set_pipeline(&mut self, passes: Vec<FilterMode>) {
+        if !passes.iter().all(FilterMode::supports_cpu) {
+            log::error!("Custom shaders are only supported on Windows.");
+            return;
+        }
+        self.passes = passes;
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    /// The overlay has no text rendering, switching filters is only logged.
+    fn set_indicator(&mut self, _text: Option<&str>) {}
+
+    fn present(&mut self) {
+        for output in 0..self.state.outputs.len() {
+            if let Err(err) = self.show_filtered(output) {
+                log::error!("Unable to present frame: {}", err);
+            }
+        }
+        let _ = self.conn.flush();
+    }
+}
+
+fn bind_output(registry: &WlRegistry, name: u32, version: u32, qh: &QueueHandle<State>) -> Output {
+    Output {
+        name,
+        output: registry.bind(name, version.min(4), qh, name),
+        position: (0, 0),
+        size: (0, 0),
+        scale: 1,
+        overlay: None,
+        shown: None,
+        clear: None,
+        captured: None,
+        frame: Vec::new(),
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == WlOutput::interface().name => {
+                state.outputs.push(bind_output(registry, name, version, qh));
+                state.outputs_changed = true;
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some(i) = state.outputs.iter().position(|o| o.name == name) {
+                    let output = state.outputs.remove(i);
+                    if output.output.version() >= 3 {
+                        output.output.release();
+                    }
+                    state.outputs_changed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, u32> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlOutput,
+        event: wl_output::Event,
+        name: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state.output(*name) else {
+            return;
+        };
+        let mut changed = false;
+        match event {
+            wl_output::Event::Geometry { x, y, .. } => {
+                changed = output.position != (x, y);
+                output.position = (x, y);
+            }
+            wl_output::Event::Mode {
+                flags: WEnum::Value(flags),
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => {
+                let size = (width as u32, height as u32);
+                changed = output.size != (0, 0) && output.size != size;
+                output.size = size;
+            }
+            wl_output::Event::Scale { factor } => {
+                changed = output.scale != factor;
+                output.scale = factor;
+            }
+            _ => {}
+        }
+        state.outputs_changed |= changed;
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, u32> for State {
+    fn event(
+        state: &mut Self,
+        layer: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        name: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state.output(*name) else {
+            return;
+        };
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                layer.ack_configure(serial);
+                if let Some(overlay) = &mut output.overlay {
+                    overlay.configured = true;
+                }
+            }
+            zwlr_layer_surface_v1::Event::Closed => output.overlay = None,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let capture = &mut state.capture;
+        match event {
+            zwlr_screencopy_frame_v1::Event::Buffer {
+                format,
+                width,
+                height,
+                stride,
+            } => capture.buffer = Some((format, width, height, stride)),
+            zwlr_screencopy_frame_v1::Event::BufferDone => capture.buffer_done = true,
+            zwlr_screencopy_frame_v1::Event::Flags { flags } => {
+                capture.y_invert = matches!(flags, WEnum::Value(f) if f.contains(zwlr_screencopy_frame_v1::Flags::YInvert));
+            }
+            zwlr_screencopy_frame_v1::Event::Ready { .. } => capture.result = Some(true),
+            zwlr_screencopy_frame_v1::Event::Failed => capture.result = Some(false),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlCallback, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlCallback,
+        event: wl_callback::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            state.frame_done = true;
+        }
+    }
+}
+
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: WlRegion);
+delegate_noop!(State: WlShmPool);
+delegate_noop!(State: ZwlrLayerShellV1);
+delegate_noop!(State: ZwlrScreencopyManagerV1);
+delegate_noop!(State: ignore WlShm);
+delegate_noop!(State: ignore WlBuffer);
+delegate_noop!(State: ignore WlSurface);
+
+/// Signals sent instead of each hotkey, `SIGRTMIN + n` for the hue and mode
+/// ones and `SIGRTMIN + 7 + n` for the n-th of `profiles`, as far as they go.
+fn hotkey_signals(profiles: usize) -> Vec<(i32, Action)> {
+    let rt = libc::SIGRTMIN();
+    let profiles = (1..=profiles)
+        .map(|i| (rt + 7 + i as i32, Action::Profile(i)))
+        .take_while(|(signal, _)| *signal <= libc::SIGRTMAX());
+    [
+        (SIGUSR1, Action::Toggle),
+        (SIGUSR2, Action::Freeze),
+        (rt + 1, Action::HueNext),
+        (rt + 2, Action::HuePrev),
+        (rt + 3, Action::HueWider),
+        (rt + 4, Action::HueNarrower),
+        (rt + 5, Action::NextMode),
+        (rt + 6, Action::PrevMode),
+        (rt + 7, Action::Reset),
+    ]
+    .into_iter()
+    .chain(profiles)
+    .collect()
+}
+
+/// Wayland doesn't let clients grab keys, so the hotkeys are left to the
+/// compositor, which sends the [`hotkey_signals`] until `terminator` fires.
+pub fn hotkey_loop(profiles: usize, terminator: Receiver<()>) -> Result<(), Error> {
+    let bindings = hotkey_signals(profiles);
+    let mut signals = Signals::new(bindings.iter().map(|(signal, _)| *signal))?;
+    let handle = signals.handle();
+    std::thread::spawn(move || {
+        let _ = terminator.recv();
+        handle.close();
+    });
+
+    for signal in signals.forever() {
+        if let Some((_, action)) = bindings.iter().find(|(s, _)| *s == signal) {
+            action.run();
+        }
+    }
+    log::info!("Terminating hotkey loop.");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::render::RenderLoop;
+
+    #[test]
+    #[ignore = "needs a wlroots compositor, run with `WLR_BACKENDS=headless sway` and `cargo test -- --ignored`"]
+    fn headless_enable_and_disable() {
+        let mut backend =
+            WaylandBackend::new(vec![FilterMode::LabGrayscale], Transform::None).unwrap();
+        assert!(!backend.outputs().is_empty());
+        let mut state = RenderLoop::new(&mut backend, 60);
+        let now = Instant::now();
+
+        state.step(&mut backend, true, false, now);
+        assert!(
+            backend
+                .state
+                .outputs
+                .iter()
+                .all(|o| o.overlay.as_ref().is_some_and(|o| o.configured))
+        );
+
+        // The overlay covers the output, so it's all gray now.
+        backend.queue.roundtrip(&mut backend.state).unwrap();
+        backend.capture(0).unwrap();
+        assert!(
+            backend.state.outputs[0]
+                .frame
+                .chunks_exact(4)
+                .all(|px| px[0] == px[1] && px[1] == px[2])
+        );
+
+        state.step(&mut backend, false, false, now + Duration::from_secs(1));
+        assert!(backend.state.outputs.iter().all(|o| o.overlay.is_none()));
+    }
+}
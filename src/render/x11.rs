@@ -0,0 +1,851 @@
+//! X11 backend.
+//!
+//! The overlay is an override-redirect window, so it can't be excluded from a
+//! capture of the root window. Instead every top-level window is redirected
+//! with the Composite extension and the desktop is assembled from their
+//! pixmaps, leaving the overlay out.
+
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, TryRecvError};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::errors::ReplyOrIdError;
+use x11rb::protocol::Event;
+use x11rb::protocol::composite::{self, ConnectionExt as _};
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::shape::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::*;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+use crate::APP_ID;
+use crate::color;
+use crate::config::{Behavior, Binding, FilterMode, Key, Modifier, Transform};
+use crate::render::{
+    Action, CaptureError, CaptureSource, INDICATOR_SIZE, OutputInfo, Presenter, Rect,
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+impl From<ReplyOrIdError> for CaptureError {
+    fn from(err: ReplyOrIdError) -> Self {
+        CaptureError::Other(err.into())
+    }
+}
+
+impl From<x11rb::errors::ReplyError> for CaptureError {
+    fn from(err: x11rb::errors::ReplyError) -> Self {
+        CaptureError::Other(err.into())
+    }
+}
+
+impl From<x11rb::errors::ConnectionError> for CaptureError {
+    fn from(err: x11rb::errors::ConnectionError) -> Self {
+        CaptureError::Other(err.into())
+    }
+}
+
+pub struct X11Backend {
+    conn: RustConnection,
+    root: Window,
+    depth: u8,
+    root_size: (u16, u16),
+    window: Window,
+    gc: Gcontext,
+    /// Window and graphics context of the indicator, created on first use.
+    indicator: Option<(Window, Gcontext)>,
+    passes: Vec<FilterMode>,
+    transform: Transform,
+    outputs: Vec<OutputInfo>,
+    /// RGBA8 of each output, filled by [`CaptureSource::acquire_frame`].
+    frames: Vec<Vec<u8>>,
+}
+
+impl X11Backend {
+    pub fn new(passes: Vec<FilterMode>, transform: Transform) -> Result<Self, Error> {
+        if !passes.iter().all(FilterMode::supports_cpu) {
+            return Err("Custom shaders are only supported on Windows".into());
+        }
+
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let screen = &conn.setup().roots[screen_num];
+        let root = screen.root;
+        let depth = screen.root_depth;
+        let root_size = (screen.width_in_pixels, screen.height_in_pixels);
+
+        // Frames are converted assuming 32 bit little endian BGRX pixels.
+        let bpp = conn
+            .setup()
+            .pixmap_formats
+            .iter()
+            .find(|f| f.depth == depth)
+            .map(|f| f.bits_per_pixel);
+        if bpp != Some(32) || conn.setup().image_byte_order != ImageOrder::LSB_FIRST {
+            return Err(format!("Unsupported pixel format of depth {}", depth).into());
+        }
+
+        if conn
+            .extension_information(composite::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Err("The X server doesn't support the Composite extension".into());
+        }
+        conn.composite_query_version(0, 4)?.reply()?;
+        // Automatic redirection can be shared with a running compositor.
+        conn.composite_redirect_subwindows(root, composite::Redirect::AUTOMATIC)?;
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            root_size.0,
+            root_size.1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .background_pixmap(x11rb::NONE),
+        )?;
+        conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            APP_ID.as_bytes(),
+        )?;
+        // An empty input region lets every click through.
+        conn.shape_rectangles(
+            shape::SO::SET,
+            shape::SK::INPUT,
+            ClipOrdering::UNSORTED,
+            window,
+            0,
+            0,
+            &[],
+        )?;
+
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, window, &CreateGCAux::new())?;
+        conn.flush()?;
+
+        let mut backend = Self {
+            conn,
+            root,
+            depth,
+            root_size,
+            window,
+            gc,
+            indicator: None,
+            passes,
+            transform,
+            outputs: Vec::new(),
+            frames: Vec::new(),
+        };
+        backend.enumerate_outputs();
+        Ok(backend)
+    }
+
+    fn enumerate_outputs(&mut self) {
+        let monitors = match self.conn.randr_get_monitors(self.root, true) {
+            Ok(cookie) => cookie.reply().map(|r| r.monitors).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+
+        self.outputs = monitors
+            .iter()
+            .map(|m| OutputInfo {
+                desktop_rect: Rect {
+                    left: m.x as i32,
+                    top: m.y as i32,
+                    right: m.x as i32 + m.width as i32,
+                    bottom: m.y as i32 + m.height as i32,
+                },
+                width: m.width as u32,
+                height: m.height as u32,
+            })
+            .collect();
+
+        // Without RandR the whole screen is one output.
+        if self.outputs.is_empty() {
+            let (width, height) = self.root_size;
+            self.outputs.push(OutputInfo {
+                desktop_rect: Rect {
+                    left: 0,
+                    top: 0,
+                    right: width as i32,
+                    bottom: height as i32,
+                },
+                width: width as u32,
+                height: height as u32,
+            });
+        }
+
+        self.frames = self
+            .outputs
+            .iter()
+            .map(|o| vec![0; o.width as usize * o.height as usize * 4])
+            .collect();
+    }
+
+    /// Copies the part of `drawable` at `src` to `dst` in the RGBA8 `frame`.
+    fn blit(
+        &self,
+        frame: &mut [u8],
+        frame_width: u32,
+        drawable: Drawable,
+        src: (i16, i16),
+        dst: (u32, u32),
+        size: (u16, u16),
+    ) -> Result<(), CaptureError> {
+        let image = self
+            .conn
+            .get_image(
+                ImageFormat::Z_PIXMAP,
+                drawable,
+                src.0,
+                src.1,
+                size.0,
+                size.1,
+                !0,
+            )?
+            .reply()?;
+        let has_alpha = image.depth == 32;
+
+        for y in 0..size.1 as usize {
+            let src_row = &image.data[y * size.0 as usize * 4..][..size.0 as usize * 4];
+            let dst_start = ((dst.1 as usize + y) * frame_width as usize + dst.0 as usize) * 4;
+            let dst_row = &mut frame[dst_start..][..size.0 as usize * 4];
+
+            for (s, d) in src_row.chunks_exact(4).zip(dst_row.chunks_exact_mut(4)) {
+                let [b, g, r, a] = [s[0], s[1], s[2], s[3]];
+                if has_alpha && a != 255 {
+                    // Premultiplied ARGB windows.
+                    let inv = 255 - a as u32;
+                    d[0] = (r as u32 + d[0] as u32 * inv / 255).min(255) as u8;
+                    d[1] = (g as u32 + d[1] as u32 * inv / 255).min(255) as u8;
+                    d[2] = (b as u32 + d[2] as u32 * inv / 255).min(255) as u8;
+                } else {
+                    d[0] = r;
+                    d[1] = g;
+                    d[2] = b;
+                }
+                d[3] = 255;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn root_background(&self) -> Option<Pixmap> {
+        let atom = self
+            .conn
+            .intern_atom(true, b"_XROOTPMAP_ID")
+            .ok()?
+            .reply()
+            .ok()?
+            .atom;
+        if atom == x11rb::NONE {
+            return None;
+        }
+        let reply = self
+            .conn
+            .get_property(false, self.root, atom, AtomEnum::PIXMAP, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        reply.value32()?.next()
+    }
+
+    /// A click-through window with white on black text in the `fixed` core
+    /// font, which every X server has.
+    fn create_indicator(&self) -> Result<(Window, Gcontext), Error> {
+        let window = self.conn.generate_id()?;
+        self.conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            self.root,
+            0,
+            0,
+            INDICATOR_SIZE.0,
+            INDICATOR_SIZE.1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .background_pixel(0),
+        )?;
+        self.conn.shape_rectangles(
+            shape::SO::SET,
+            shape::SK::INPUT,
+            ClipOrdering::UNSORTED,
+            window,
+            0,
+            0,
+            &[],
+        )?;
+
+        let font = self.conn.generate_id()?;
+        self.conn.open_font(font, b"fixed")?;
+        let gc = self.conn.generate_id()?;
+        // BGRX, like the frames.
+        self.conn.create_gc(
+            gc,
+            window,
+            &CreateGCAux::new()
+                .foreground(0xffffff)
+                .background(0)
+                .font(font),
+        )?;
+        self.conn.close_font(font)?;
+        Ok((window, gc))
+    }
+
+    fn show_indicator(&mut self, text: &str) -> Result<(), Error> {
+        let (window, gc) = match self.indicator {
+            Some(indicator) => indicator,
+            None => *self.indicator.insert(self.create_indicator()?),
+        };
+        let area = self
+            .outputs
+            .first()
+            .map(|o| o.desktop_rect)
+            .unwrap_or_default();
+        let (width, height) = (INDICATOR_SIZE.0 as i32, INDICATOR_SIZE.1 as i32);
+        self.conn.configure_window(
+            window,
+            &ConfigureWindowAux::new()
+                .x(area.left + (area.right - area.left - width) / 2)
+                .y(area.top + height)
+                .stack_mode(StackMode::ABOVE),
+        )?;
+        self.conn.map_window(window)?;
+        self.conn.clear_area(false, window, 0, 0, 0, 0)?;
+
+        // Core fonts only cover Latin-1, and `fixed` is 6 pixels wide.
+        let text: Vec<u8> = text
+            .chars()
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+            .take(255)
+            .collect();
+        let x = ((width - text.len() as i32 * 6) / 2).max(4);
+        self.conn
+            .image_text8(window, gc, x as i16, (height / 2 + 4) as i16, &text)?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// Draws every visible top-level window except the overlay, bottom to top.
+    fn compose(&self, output: usize, frame: &mut [u8]) -> Result<(), CaptureError> {
+        let out = self.outputs[output];
+        let area = out.desktop_rect;
+
+        frame.fill(0);
+        if let Some(background) = self.root_background() {
+            // The wallpaper pixmap may be gone or smaller than the screen.
+            let _ = self.blit(
+                frame,
+                out.width,
+                background,
+                (area.left as i16, area.top as i16),
+                (0, 0),
+                (out.width as u16, out.height as u16),
+            );
+        }
+
+        let children = self.conn.query_tree(self.root)?.reply()?.children;
+        for child in children {
+            if child == self.window || self.indicator.is_some_and(|(w, _)| w == child) {
+                continue;
+            }
+
+            // Windows may vanish at any time, so errors only skip the window.
+            let Ok(attrs) = self.conn.get_window_attributes(child)?.reply() else {
+                continue;
+            };
+            if attrs.map_state != MapState::VIEWABLE || attrs.class == WindowClass::INPUT_ONLY {
+                continue;
+            }
+            let Ok(geom) = self.conn.get_geometry(child)?.reply() else {
+                continue;
+            };
+
+            let border = geom.border_width as i32;
+            let win = Rect {
+                left: geom.x as i32,
+                top: geom.y as i32,
+                right: geom.x as i32 + geom.width as i32 + border * 2,
+                bottom: geom.y as i32 + geom.height as i32 + border * 2,
+            };
+            let visible = Rect {
+                left: win.left.max(area.left),
+                top: win.top.max(area.top),
+                right: win.right.min(area.right),
+                bottom: win.bottom.min(area.bottom),
+            };
+            if visible.left >= visible.right || visible.top >= visible.bottom {
+                continue;
+            }
+
+            let pixmap = self.conn.generate_id()?;
+            self.conn.composite_name_window_pixmap(child, pixmap)?;
+            let _ = self.blit(
+                frame,
+                out.width,
+                pixmap,
+                (
+                    (visible.left - win.left) as i16,
+                    (visible.top - win.top) as i16,
+                ),
+                (
+                    (visible.left - area.left) as u32,
+                    (visible.top - area.top) as u32,
+                ),
+                (
+                    (visible.right - visible.left) as u16,
+                    (visible.bottom - visible.top) as u16,
+                ),
+            );
+            self.conn.free_pixmap(pixmap)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for X11Backend {
+    fn drop(&mut self) {
+        let _ = self.conn.destroy_window(self.window);
+        if let Some((window, _)) = self.indicator {
+            let _ = self.conn.destroy_window(window);
+        }
+        let _ = self.conn.flush();
+    }
+}
+
+impl CaptureSource for X11Backend {
+    fn outputs(&self) -> Vec<OutputInfo> {
+        self.outputs.clone()
+    }
+
+    /// X11 has no cheap way to tell what changed, so the whole output is
+    /// always reported.
+    fn acquire_frame(&mut self, output: usize) -> Result<Vec<Rect>, CaptureError> {
+        // Outputs are rearranged, like DXGI losing access on a mode change.
+        if output == 0 {
+            let geom = self.conn.get_geometry(self.root)?.reply()?;
+            if (geom.width, geom.height) != self.root_size {
+                return Err(CaptureError::AccessLost);
+            }
+        }
+
+        let mut frame = std::mem::take(&mut self.frames[output]);
+        let result = self.compose(output, &mut frame);
+        self.frames[output] = frame;
+        result?;
+
+        Ok(vec![self.outputs[output].desktop_rect])
+    }
+
+    fn reinit(&mut self) -> Result<(), CaptureError> {
+        let geom = self.conn.get_geometry(self.root)?.reply()?;
+        self.root_size = (geom.width, geom.height);
+        self.conn.configure_window(
+            self.window,
+            &ConfigureWindowAux::new()
+                .width(geom.width as u32)
+                .height(geom.height as u32),
+        )?;
+        self.enumerate_outputs();
+        Ok(())
+    }
+
+    fn cursor(&self) -> Option<(i32, i32)> {
+        let pointer = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+        Some((pointer.root_x as i32, pointer.root_y as i32))
+    }
+
+    fn pixel(&self, x: i32, y: i32) -> Option<[u8; 3]> {
+        let (output, frame) = self
+            .outputs
+            .iter()
+            .zip(&self.frames)
+            .find(|(output, _)| output.desktop_rect.contains(x, y))?;
+        let rect = output.desktop_rect;
+        let i = ((y - rect.top) as usize * output.width as usize + (x - rect.left) as usize) * 4;
+        Some([frame[i], frame[i + 1], frame[i + 2]])
+    }
+}
+
+impl Presenter for X11Backend {
+    fn set_visible(&mut self, visible: bool) {
+        if visible {
+            let _ = self.conn.map_window(self.window);
+            let _ = self.conn.configure_window(
+                self.window,
+                &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+            );
+        } else {
+            let _ = self.conn.unmap_window(self.window);
+        }
+        let _ = self.conn.flush();
+    }
+
+    /// X11 has no way to hide a window from screenshots, the overlay always
+    /// shows up in them.
+    fn set_exclude_from_capture(&mut self, _exclude: bool) {}
+
+    fn set_pipeline(&mut self, passes: Vec<FilterMode>) {
+        if !passes.iter().all(FilterMode::supports_cpu) {
+            log::error!("Custom shaders are only supported on Windows.");
+            return;
+        }
+        self.passes = passes;
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    fn set_indicator(&mut self, text: Option<&str>) {
+        match (text, self.indicator) {
+            (Some(text), _) => {
+                if let Err(err) = self.show_indicator(text) {
+                    log::error!("Unable to show the indicator: {}", err);
+                }
+            }
+            (None, Some((window, _))) => {
+                let _ = self.conn.unmap_window(window);
+                let _ = self.conn.flush();
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn present(&mut self) {
+        // Stay above windows mapped since the last frame, below the indicator.
+        let _ = self.conn.configure_window(
+            self.window,
+            &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+        );
+        if let Some((window, _)) = self.indicator {
+            let _ = self.conn.configure_window(
+                window,
+                &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+            );
+        }
+
+        let max_bytes = self.conn.maximum_request_bytes() - 64;
+        for (out, frame) in self.outputs.iter().zip(&self.frames) {
+            let mut data = frame.clone();
+            // Mirrored first, so the heatmap legend stays upright.
+            self.transform.apply_cpu(&mut data, out.width, out.height);
+            color::apply_pipeline(&self.passes, &mut data, out.width, out.height);
+
+            // RGBA to BGRX.
+            for px in data.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+
+            let row_bytes = out.width as usize * 4;
+            let rows_per_request = (max_bytes / row_bytes).max(1);
+            for (i, rows) in data.chunks(rows_per_request * row_bytes).enumerate() {
+                let _ = self.conn.put_image(
+                    ImageFormat::Z_PIXMAP,
+                    self.window,
+                    self.gc,
+                    out.width as u16,
+                    (rows.len() / row_bytes) as u16,
+                    out.desktop_rect.left as i16,
+                    (out.desktop_rect.top + (i * rows_per_request) as i32) as i16,
+                    0,
+                    self.depth,
+                    rows,
+                );
+            }
+        }
+        let _ = self.conn.flush();
+    }
+}
+
+fn mod_mask(mods: &[Modifier]) -> ModMask {
+    mods.iter().fold(ModMask::from(0u16), |mask, m| {
+        mask | match m {
+            Modifier::Alt => ModMask::M1,
+            Modifier::Ctrl => ModMask::CONTROL,
+            Modifier::Shift => ModMask::SHIFT,
+            Modifier::Win => ModMask::M4,
+            Modifier::NoRepeat => ModMask::from(0u16),
+        }
+    })
+}
+
+/// The X11 keysym of `key`.
+fn keysym(key: Key) -> u32 {
+    match key {
+        // Latin-1 keysyms are the same as the lowercase character.
+        Key::Char(ch) => ch.to_ascii_lowercase() as u32,
+        Key::Function(n) => 0xFFBD + n as u32,
+        Key::Numpad(n) => 0xFFB0 + n as u32,
+        Key::NumpadMultiply => 0xFFAA,
+        Key::NumpadAdd => 0xFFAB,
+        Key::NumpadSubtract => 0xFFAD,
+        Key::NumpadDecimal => 0xFFAE,
+        Key::NumpadDivide => 0xFFAF,
+        Key::Home => 0xFF50,
+        Key::Left => 0xFF51,
+        Key::Up => 0xFF52,
+        Key::Right => 0xFF53,
+        Key::Down => 0xFF54,
+        Key::PageUp => 0xFF55,
+        Key::PageDown => 0xFF56,
+        Key::End => 0xFF57,
+        Key::Insert => 0xFF63,
+        Key::Delete => 0xFFFF,
+        Key::Escape => 0xFF1B,
+        Key::Tab => 0xFF09,
+        Key::Space => 0x20,
+        Key::Enter => 0xFF0D,
+        Key::Backspace => 0xFF08,
+        Key::Pause => 0xFF13,
+        Key::PrintScreen => 0xFF61,
+        Key::ScrollLock => 0xFF14,
+        Key::CapsLock => 0xFFE5,
+        Key::NumLock => 0xFF7F,
+        Key::Semicolon => ';' as u32,
+        Key::Equals => '=' as u32,
+        Key::Plus => '+' as u32,
+        Key::Comma => ',' as u32,
+        Key::Minus => '-' as u32,
+        Key::Period => '.' as u32,
+        Key::Slash => '/' as u32,
+        Key::Backquote => '`' as u32,
+        Key::LeftBracket => '[' as u32,
+        Key::Backslash => '\\' as u32,
+        Key::RightBracket => ']' as u32,
+        Key::Quote => '\'' as u32,
+    }
+}
+
+/// Finds the keycode producing the keysym of `key`.
+fn keycode(conn: &RustConnection, key: Key) -> Result<Keycode, Error> {
+    let keysym = keysym(key);
+    let setup = conn.setup();
+    let count = setup.max_keycode - setup.min_keycode + 1;
+    let mapping = conn
+        .get_keyboard_mapping(setup.min_keycode, count)?
+        .reply()?;
+
+    mapping
+        .keysyms
+        .chunks(mapping.keysyms_per_keycode as usize)
+        .position(|syms| syms.contains(&keysym))
+        .map(|i| setup.min_keycode + i as u8)
+        .ok_or_else(|| format!("No keycode for key {}", key).into())
+}
+
+/// Grabs the hotkeys on the root window and runs their actions until
+/// `terminator` fires.
+pub fn hotkey_loop(hotkeys: &[(Binding, Action)], terminator: Receiver<()>) -> Result<(), Error> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let bindings = hotkeys
+        .iter()
+        .map(|(binding, action)| {
+            let keys = &binding.keys;
+            Ok((keycode(&conn, keys.key)?, mod_mask(&keys.mods), *action))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let holds = hotkeys
+        .iter()
+        .map(|(binding, _)| binding.behavior == Behavior::Hold)
+        .collect::<Vec<_>>();
+    let mut held = vec![false; bindings.len()];
+    // Caps Lock and Num Lock would otherwise break the grabs.
+    let ignored = [
+        ModMask::from(0u16),
+        ModMask::LOCK,
+        ModMask::M2,
+        ModMask::LOCK | ModMask::M2,
+    ];
+    for (code, mask, _) in &bindings {
+        for extra in ignored {
+            conn.grab_key(
+                false,
+                root,
+                *mask | extra,
+                *code,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?
+            .check()
+            .map_err(|err| format!("Unable to grab hotkey: {}", err))?;
+        }
+    }
+    conn.flush()?;
+
+    let relevant = ModMask::CONTROL | ModMask::SHIFT | ModMask::M1 | ModMask::M4;
+    loop {
+        match terminator.try_recv() {
+            Ok(()) | Err(TryRecvError::Disconnected) => {
+                log::info!("Terminating hotkey loop.");
+                break;
+            }
+            Err(TryRecvError::Empty) => {}
+        }
+
+        let mut pending = None;
+        while let Some(event) = pending
+            .take()
+            .map_or_else(|| conn.poll_for_event(), |e| Ok(Some(e)))?
+        {
+            match event {
+                Event::KeyPress(event) => {
+                    let state = ModMask::from(u16::from(event.state)) & relevant;
+                    for (i, (code, mask, action)) in bindings.iter().enumerate() {
+                        if event.detail != *code || state != *mask {
+                            continue;
+                        }
+                        if !holds[i] {
+                            action.run();
+                        } else if !held[i] {
+                            held[i] = true;
+                            action.hold();
+                        }
+                    }
+                }
+                Event::KeyRelease(event) => {
+                    // Auto-repeat sends a release immediately followed by a
+                    // press with the same timestamp, the key is still down.
+                    match conn.poll_for_event()? {
+                        Some(Event::KeyPress(next))
+                            if next.detail == event.detail && next.time == event.time =>
+                        {
+                            continue;
+                        }
+                        next => pending = next,
+                    }
+                    for (i, (code, _, action)) in bindings.iter().enumerate() {
+                        if event.detail == *code && held[i] {
+                            held[i] = false;
+                            action.release();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    for (i, (code, mask, action)) in bindings.iter().enumerate() {
+        if held[i] {
+            action.release();
+        }
+        for extra in ignored {
+            conn.ungrab_key(*code, root, *mask | extra)?;
+        }
+    }
+    conn.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::render::RenderLoop;
+
+    fn overlay_pixel(backend: &X11Backend, x: i16, y: i16) -> [u8; 3] {
+        let image = backend
+            .conn
+            .get_image(ImageFormat::Z_PIXMAP, backend.window, x, y, 1, 1, !0)
+            .unwrap()
+            .reply()
+            .unwrap();
+        [image.data[2], image.data[1], image.data[0]]
+    }
+
+    #[test]
+    fn keysyms() {
+        assert_eq!(keysym(Key::Char('C')), 0x63);
+        assert_eq!(keysym(Key::Function(1)), 0xFFBE);
+        assert_eq!(keysym(Key::Function(12)), 0xFFC9);
+        assert_eq!(keysym(Key::Numpad(5)), 0xFFB5);
+        assert_eq!(keysym(Key::LeftBracket), 0x5B);
+        assert_eq!(keysym(Key::Plus), 0x2B);
+    }
+
+    #[test]
+    #[ignore = "needs an X server, run with `xvfb-run cargo test -- --ignored`"]
+    fn xvfb_enable_and_freeze() {
+        let (conn, screen_num) = x11rb::connect(None).unwrap();
+        let screen = conn.setup().roots[screen_num].clone();
+
+        let red = conn.generate_id().unwrap();
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            red,
+            screen.root,
+            0,
+            0,
+            64,
+            64,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .background_pixel(0xff0000),
+        )
+        .unwrap();
+        conn.map_window(red).unwrap();
+        conn.sync().unwrap();
+
+        let mut backend = X11Backend::new(vec![FilterMode::LabGrayscale], Transform::None).unwrap();
+        let mut state = RenderLoop::new(&mut backend, 60);
+        let now = Instant::now();
+
+        state.step(&mut backend, true, false, now);
+        let attrs = backend
+            .conn
+            .get_window_attributes(backend.window)
+            .unwrap()
+            .reply()
+            .unwrap();
+        assert_eq!(attrs.map_state, MapState::VIEWABLE);
+        assert_eq!(overlay_pixel(&backend, 8, 8), [136, 136, 136]);
+
+        // A frozen overlay keeps showing the old frame.
+        conn.change_window_attributes(
+            red,
+            &ChangeWindowAttributesAux::new().background_pixel(0xff),
+        )
+        .unwrap();
+        conn.clear_area(false, red, 0, 0, 0, 0).unwrap();
+        conn.sync().unwrap();
+        state.step(&mut backend, true, true, now + Duration::from_secs(1));
+        assert_eq!(overlay_pixel(&backend, 8, 8), [136, 136, 136]);
+
+        // Blue's L* is 32.3.
+        state.step(&mut backend, true, false, now + Duration::from_secs(2));
+        assert_eq!(overlay_pixel(&backend, 8, 8), [82, 82, 82]);
+
+        state.step(&mut backend, false, false, now + Duration::from_secs(3));
+        let attrs = backend
+            .conn
+            .get_window_attributes(backend.window)
+            .unwrap()
+            .reply()
+            .unwrap();
+        assert_eq!(attrs.map_state, MapState::UNMAPPED);
+    }
+}
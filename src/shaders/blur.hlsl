@@ -0,0 +1,43 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// One pass of a separable Gaussian blur in linear light, drawn once along each
+// axis.
+
+cbuffer Params : register(b0) {
+    // (1, 0) or (0, 1).
+    float2 direction;
+    float sigma;
+    // Samples taken on each side of a pixel.
+    int radius;
+};
+
+float gamma_to_linear(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.04045) return x / 12.92;
+    return pow((x + 0.055) / 1.055, 2.4);
+}
+
+float linear_to_gamma(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.0031308) return x * 12.92;
+    return 1.055 * pow(x, 1.0 / 2.4) - 0.055;
+}
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float2 size;
+    screenTexture.GetDimensions(size.x, size.y);
+    float2 texel = direction / size;
+
+    float3 sum = float3(0.0, 0.0, 0.0);
+    float total = 0.0;
+    for (int i = -radius; i <= radius; i++) {
+        float w = exp(-0.5 * i * i / (sigma * sigma));
+        float4 c = screenTexture.Sample(samplerState, tex + texel * i);
+        sum += w * float3(gamma_to_linear(c.r), gamma_to_linear(c.g), gamma_to_linear(c.b));
+        total += w;
+    }
+
+    float3 rgb = sum / total;
+    return float4(linear_to_gamma(rgb.r), linear_to_gamma(rgb.g), linear_to_gamma(rgb.b), 1.0f);
+}
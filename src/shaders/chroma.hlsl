@@ -0,0 +1,93 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Color model converting code is translated from `bevy` project.
+// Here's the original license:
+// 
+// MIT License
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+cbuffer Params : register(b0) {
+    // OkLCh or CIE LCh.
+    uint model;
+    uint stops;
+    uint clip;
+    float threshold;
+    float3 clip_color;
+    float3 colors[16];
+};
+
+static const float OKLCH_MAX_CHROMA = 0.33;
+static const float LAB_MAX_CHROMA = 134.0;
+
+float gamma_to_linear(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.04045) return x / 12.92;
+    return pow((x + 0.055) / 1.055, 2.4);
+}
+
+float oklch_chroma(float red, float green, float blue) {
+    float l = 0.41222146 * red + 0.53633255 * green + 0.051445995 * blue;
+    float m = 0.2119035 * red + 0.6806995 * green + 0.10739696 * blue;
+    float s = 0.08830246 * red + 0.28171885 * green + 0.6299787 * blue;
+    float l_ = pow(l, 1.0 / 3.0);
+    float m_ = pow(m, 1.0 / 3.0);
+    float s_ = pow(s, 1.0 / 3.0);
+    float a = 1.9779985 * l_ - 2.4285922 * m_ + 0.4505937 * s_;
+    float b = 0.025904037 * l_ + 0.78277177 * m_ - 0.80867577 * s_;
+    return sqrt(a * a + b * b);
+}
+
+float lab_f(float t) {
+    const float CIE_EPSILON = 216.0 / 24389.0;
+    const float CIE_KAPPA = 24389.0 / 27.0;
+    return t > CIE_EPSILON ? pow(t, 1.0 / 3.0) : (CIE_KAPPA * t + 16.0) / 116.0;
+}
+
+float lab_chroma(float r, float g, float b) {
+    float x = r * 0.4124564 + g * 0.3575761 + b * 0.1804375;
+    float y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    float z = r * 0.0193339 + g * 0.119192 + b * 0.9503041;
+    float fx = lab_f(x / 0.95047);
+    float fy = lab_f(y);
+    float fz = lab_f(z / 1.08883);
+    float a = 500.0 * (fx - fy);
+    float bb = 200.0 * (fy - fz);
+    return sqrt(a * a + bb * bb);
+}
+
+float3 gradient(float t) {
+    float x = saturate(t) * (stops - 1);
+    uint i = min((uint)floor(x), stops - 2);
+    return lerp(colors[i], colors[i + 1], x - i);
+}
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float4 color = screenTexture.Sample(samplerState, tex);
+    float r = gamma_to_linear(color.r);
+    float g = gamma_to_linear(color.g);
+    float b = gamma_to_linear(color.b);
+    float c = model == 0 ? oklch_chroma(r, g, b) : lab_chroma(r, g, b);
+    if (clip != 0 && c > threshold) {
+        return float4(clip_color, 1.0f);
+    }
+    float max_chroma = model == 0 ? OKLCH_MAX_CHROMA : LAB_MAX_CHROMA;
+    return float4(gradient(c / max_chroma), 1.0f);
+}
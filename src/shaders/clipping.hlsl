@@ -0,0 +1,43 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Marks crushed shadows, blown highlights and clipped channels, everything else
+// is left alone.
+
+cbuffer Params : register(b0) {
+    // 8-bit sRGB thresholds, inclusive.
+    float shadows;
+    float highlights;
+    uint channels;
+    // Hatch or tint.
+    uint hatch;
+    float3 shadow_color;
+    float3 highlight_color;
+    float3 channel_color;
+};
+
+static const uint HATCH_WIDTH = 4;
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float3 color = screenTexture.Sample(samplerState, tex).rgb;
+    float3 values = round(color * 255.0);
+    bool3 low = values <= shadows;
+    bool3 high = values >= highlights;
+
+    float3 warning;
+    if (all(low)) {
+        warning = shadow_color;
+    } else if (all(high)) {
+        warning = highlight_color;
+    } else if (channels != 0 && (any(low) || any(high))) {
+        warning = channel_color;
+    } else {
+        return float4(color, 1.0f);
+    }
+
+    if (hatch != 0) {
+        uint2 p = (uint2)pos.xy;
+        return float4((p.x + p.y) / HATCH_WIDTH % 2 == 0 ? warning : color, 1.0f);
+    }
+    return float4(lerp(color, warning, 0.5), 1.0f);
+}
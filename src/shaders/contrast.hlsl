@@ -0,0 +1,57 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Contrast of every pixel against a background, the WCAG 2.x ratio on a log
+// scale or the APCA 0.0.98G |Lc|.
+
+cbuffer Params : register(b0) {
+    // WCAG or APCA.
+    uint apca;
+    float3 background;
+};
+
+static const float APCA_MAX = 108.0;
+
+float gamma_to_linear(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.04045) return x / 12.92;
+    return pow((x + 0.055) / 1.055, 2.4);
+}
+
+float relative_luminance(float3 color) {
+    float r = gamma_to_linear(color.r);
+    float g = gamma_to_linear(color.g);
+    float b = gamma_to_linear(color.b);
+    return r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+}
+
+float apca_luminance(float3 color) {
+    float3 c = pow(max(color, 0.0), 2.4);
+    float y = c.r * 0.2126729 + c.g * 0.7151522 + c.b * 0.072175;
+    return y > 0.022 ? y : y + pow(0.022 - y, 1.414);
+}
+
+float apca_contrast(float3 text_color, float3 background_color) {
+    float text = apca_luminance(text_color);
+    float bg = apca_luminance(background_color);
+    if (abs(bg - text) < 0.0005) return 0.0;
+    if (bg > text) {
+        float s = (pow(bg, 0.56) - pow(text, 0.57)) * 1.14;
+        return (s < 0.1 ? 0.0 : s - 0.027) * 100.0;
+    }
+    float s = (pow(bg, 0.65) - pow(text, 0.62)) * 1.14;
+    return (s > -0.1 ? 0.0 : s + 0.027) * 100.0;
+}
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float3 color = screenTexture.Sample(samplerState, tex).rgb;
+    float level;
+    if (apca != 0) {
+        level = abs(apca_contrast(color, background)) / APCA_MAX;
+    } else {
+        float a = relative_luminance(color);
+        float b = relative_luminance(background);
+        level = log((max(a, b) + 0.05) / (min(a, b) + 0.05)) / log(21.0);
+    }
+    return float4(level, level, level, 1.0f);
+}
@@ -0,0 +1,60 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Simulates color vision deficiencies with the matrices from Machado, Oliveira
+// and Fernandes, "A Physiologically-based Model for Simulation of Color Vision
+// Deficiency", 2009.
+
+cbuffer Params : register(b0) {
+    // Protanopia, deuteranopia, tritanopia or achromatopsia.
+    uint deficiency;
+    float severity;
+};
+
+static const float3x3 PROTANOPIA = float3x3(
+     0.152286,  1.052583, -0.204868,
+     0.114503,  0.786281,  0.099216,
+    -0.003882, -0.048116,  1.051998);
+
+static const float3x3 DEUTERANOPIA = float3x3(
+     0.367322,  0.860646, -0.227968,
+     0.280085,  0.672501,  0.047413,
+    -0.011820,  0.042940,  0.968881);
+
+static const float3x3 TRITANOPIA = float3x3(
+     1.255528, -0.076749, -0.178779,
+    -0.078411,  0.930809,  0.147602,
+     0.004733,  0.691367,  0.303900);
+
+static const float3x3 ACHROMATOPSIA = float3x3(
+    0.2126729, 0.7151522, 0.072175,
+    0.2126729, 0.7151522, 0.072175,
+    0.2126729, 0.7151522, 0.072175);
+
+float3x3 cvd_matrix() {
+    if (deficiency == 0) return PROTANOPIA;
+    if (deficiency == 1) return DEUTERANOPIA;
+    if (deficiency == 2) return TRITANOPIA;
+    return ACHROMATOPSIA;
+}
+
+float gamma_to_linear(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.04045) return x / 12.92;
+    return pow((x + 0.055) / 1.055, 2.4);
+}
+
+float linear_to_gamma(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.0031308) return x * 12.92;
+    return 1.055 * pow(x, 1.0 / 2.4) - 0.055;
+}
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float4 color = screenTexture.Sample(samplerState, tex);
+    float3 rgb = float3(gamma_to_linear(color.r), gamma_to_linear(color.g), gamma_to_linear(color.b));
+
+    float3 simulated = saturate(mul(cvd_matrix(), rgb));
+    rgb = lerp(rgb, simulated, severity);
+    return float4(linear_to_gamma(rgb.r), linear_to_gamma(rgb.g), linear_to_gamma(rgb.b), 1.0f);
+}
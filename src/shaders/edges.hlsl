@@ -0,0 +1,98 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Color model converting code is translated from `bevy` project.
+// Here's the original license:
+// 
+// MIT License
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// Sobel edges of the lightness, sampling the neighbors `thickness` pixels
+// apart.
+
+cbuffer Params : register(b0) {
+    // OkLab or Lab.
+    uint lightness;
+    float threshold;
+    float thickness;
+    uint invert;
+    uint overlay;
+};
+
+float gamma_to_linear(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.04045) return x / 12.92;
+    return pow((x + 0.055) / 1.055, 2.4);
+}
+
+float toe(float x) {
+    const float K1 = 0.206;
+    const float K2 = 0.03;
+    const float K3 = (1.0 + K1) / (1.0 + K2);
+    return 0.5 * (K3 * x - K1 + sqrt((K3 * x - K1) * (K3 * x - K1) + 4.0 * K2 * K3 * x));
+}
+
+float oklab_lightness(float red, float green, float blue) {
+    float l = 0.41222146 * red + 0.53633255 * green + 0.051445995 * blue;
+    float m = 0.2119035 * red + 0.6806995 * green + 0.10739696 * blue;
+    float s = 0.08830246 * red + 0.28171885 * green + 0.6299787 * blue;
+    float l_ = pow(l, 1.0 / 3.0);
+    float m_ = pow(m, 1.0 / 3.0);
+    float s_ = pow(s, 1.0 / 3.0);
+    return toe(0.21045426 * l_ + 0.7936178 * m_ - 0.004072047 * s_);
+}
+
+float lab_lightness(float r, float g, float b) {
+    float y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    const float CIE_EPSILON = 216.0 / 24389.0;
+    const float CIE_KAPPA = 24389.0 / 27.0;
+    float fy = y > CIE_EPSILON ? pow(y, 1.0 / 3.0) : (CIE_KAPPA * y + 16.0) / 116.0;
+    return 1.16 * fy - 0.16;
+}
+
+float lightness_at(float2 tex, float2 texel, int dx, int dy) {
+    float4 c = screenTexture.Sample(samplerState, tex + texel * float2(dx, dy));
+    float r = gamma_to_linear(c.r);
+    float g = gamma_to_linear(c.g);
+    float b = gamma_to_linear(c.b);
+    return lightness == 0 ? oklab_lightness(r, g, b) : lab_lightness(r, g, b);
+}
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float2 size;
+    screenTexture.GetDimensions(size.x, size.y);
+    float2 texel = thickness / size;
+
+    float gx = (lightness_at(tex, texel, 1, -1) + 2.0 * lightness_at(tex, texel, 1, 0) + lightness_at(tex, texel, 1, 1))
+        - (lightness_at(tex, texel, -1, -1) + 2.0 * lightness_at(tex, texel, -1, 0) + lightness_at(tex, texel, -1, 1));
+    float gy = (lightness_at(tex, texel, -1, 1) + 2.0 * lightness_at(tex, texel, 0, 1) + lightness_at(tex, texel, 1, 1))
+        - (lightness_at(tex, texel, -1, -1) + 2.0 * lightness_at(tex, texel, 0, -1) + lightness_at(tex, texel, 1, -1));
+    float strength = sqrt(gx * gx + gy * gy) / 4.0;
+
+    float line_value = invert != 0 ? 1.0 : 0.0;
+    if (strength >= threshold) {
+        return float4(line_value, line_value, line_value, 1.0f);
+    }
+    if (overlay != 0) {
+        return float4(screenTexture.Sample(samplerState, tex).rgb, 1.0f);
+    }
+    float blank = 1.0 - line_value;
+    return float4(blank, blank, blank, 1.0f);
+}
@@ -0,0 +1,98 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Color model converting code is translated from `bevy` project.
+// Here's the original license:
+// 
+// MIT License
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+cbuffer Params : register(b0) {
+    // OkLab or Lab.
+    uint lightness;
+    uint stops;
+    uint legend;
+    float3 colors[16];
+};
+
+static const float LEGEND_WIDTH = 16.0;
+static const float LEGEND_HEIGHT = 256.0;
+static const float LEGEND_MARGIN = 24.0;
+
+float gamma_to_linear(float x) {
+    if (x <= 0.0) return x;
+    if (x <= 0.04045) return x / 12.92;
+    return pow((x + 0.055) / 1.055, 2.4);
+}
+
+float toe(float x) {
+    const float K1 = 0.206;
+    const float K2 = 0.03;
+    const float K3 = (1.0 + K1) / (1.0 + K2);
+    return 0.5 * (K3 * x - K1 + sqrt((K3 * x - K1) * (K3 * x - K1) + 4.0 * K2 * K3 * x));
+}
+
+float oklab_lightness(float red, float green, float blue) {
+    float l = 0.41222146 * red + 0.53633255 * green + 0.051445995 * blue;
+    float m = 0.2119035 * red + 0.6806995 * green + 0.10739696 * blue;
+    float s = 0.08830246 * red + 0.28171885 * green + 0.6299787 * blue;
+    float l_ = pow(l, 1.0 / 3.0);
+    float m_ = pow(m, 1.0 / 3.0);
+    float s_ = pow(s, 1.0 / 3.0);
+    return toe(0.21045426 * l_ + 0.7936178 * m_ - 0.004072047 * s_);
+}
+
+float lab_lightness(float r, float g, float b) {
+    float y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    const float CIE_EPSILON = 216.0 / 24389.0;
+    const float CIE_KAPPA = 24389.0 / 27.0;
+    float fy = y > CIE_EPSILON ? pow(y, 1.0 / 3.0) : (CIE_KAPPA * y + 16.0) / 116.0;
+    return 1.16 * fy - 0.16;
+}
+
+float3 gradient(float t) {
+    float x = saturate(t) * (stops - 1);
+    uint i = min((uint)floor(x), stops - 2);
+    return lerp(colors[i], colors[i + 1], x - i);
+}
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    // The legend sits in the bottom right corner of the output, lightness
+    // going up. A mirrored draw samples with flipped coordinates, which is
+    // undone so the legend stays upright under every transform.
+    bool2 flipped = bool2(ddx(tex.x) < 0.0, ddy(tex.y) < 0.0);
+    if (legend != 0) {
+        float2 size;
+        screenTexture.GetDimensions(size.x, size.y);
+        float2 p = (flipped ? 1.0 - tex : tex) * size;
+        float right = size.x - LEGEND_MARGIN;
+        float bottom = size.y - LEGEND_MARGIN;
+        if (p.x >= right - LEGEND_WIDTH && p.x < right && p.y >= bottom - LEGEND_HEIGHT && p.y < bottom) {
+            return float4(gradient((bottom - p.y) / LEGEND_HEIGHT), 1.0f);
+        }
+    }
+
+    float4 color = screenTexture.Sample(samplerState, tex);
+    float r = gamma_to_linear(color.r);
+    float g = gamma_to_linear(color.g);
+    float b = gamma_to_linear(color.b);
+    float l = lightness == 0 ? oklab_lightness(r, g, b) : lab_lightness(r, g, b);
+    return float4(gradient(l), 1.0f);
+}
@@ -0,0 +1,22 @@
+Texture2D screenTexture : register(t0);
+SamplerState samplerState : register(s0);
+
+// Applies a `.cube` 3D LUT to the sRGB colors, red along the width, green along
+// the height and blue along the depth.
+Texture3D lut : register(t1);
+
+cbuffer Params : register(b0) {
+    float3 domain_min;
+    // Entries along each axis.
+    float size;
+    float3 domain_max;
+};
+
+float4 main(float4 pos : SV_Position, float2 tex : TEXCOORD) : SV_Target {
+    float4 color = screenTexture.Sample(samplerState, tex);
+
+    float3 x = saturate((color.rgb - domain_min) / (domain_max - domain_min));
+    // Sample at texel centers, so the domain bounds hit the first and last entries.
+    float3 uvw = (x * (size - 1.0) + 0.5) / size;
+    return float4(lut.Sample(samplerState, uvw).rgb, 1.0f);
+}